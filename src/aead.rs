@@ -0,0 +1,155 @@
+//! ChaCha20-Poly1305 (RFC 8439) under a key held in a [`Secret`].
+//!
+//! Enabled by the `aead` feature. The key page is unsealed only while the
+//! cipher state is being loaded, and the expanded state — ChaCha20's input
+//! and keystream blocks and the Poly1305 accumulator — lives in a protected
+//! scratch page that is wiped when the call returns. The key is never
+//! copied into an unprotected buffer.
+//!
+//! ```
+//! use memsafe::{Secret, aead};
+//!
+//! let mut key = Secret::<32>::random().unwrap();
+//! let nonce = [7u8; aead::NONCE_LEN]; // unique per message under this key
+//!
+//! let mut data = *b"customer record";
+//! let tag = aead::encrypt_in_place(&mut key, &nonce, b"table:users", &mut data).unwrap();
+//!
+//! aead::decrypt_in_place(&mut key, &nonce, b"table:users", &mut data, &tag).unwrap();
+//! assert_eq!(&data, b"customer record");
+//! ```
+//!
+//! A nonce must never be reused under the same key: doing so reveals the
+//! XOR of the two plaintexts and allows tag forgery. Use a counter, or 12
+//! random bytes for up to about 2^32 messages per key.
+
+use crate::cell::Cell;
+use crate::chacha::ChaCha20State;
+use crate::ct;
+use crate::poly1305::Poly1305;
+use crate::{MemoryError, Secret, SecretBytes};
+
+/// Key length in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Nonce length in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// Authentication tag length in bytes.
+pub const TAG_LEN: usize = crate::poly1305::TAG_LEN;
+
+/// Encrypt `buffer` in place and return the authentication tag over the
+/// ciphertext and `aad` (associated data, authenticated but not
+/// encrypted).
+pub fn encrypt_in_place(
+    key: &mut Secret<KEY_LEN>,
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    buffer: &mut [u8],
+) -> Result<[u8; TAG_LEN], MemoryError> {
+    let mut scratch = Scratch::new(key, nonce)?;
+    scratch.chacha.apply_keystream(buffer);
+    scratch.authenticate(aad, buffer);
+    Ok(scratch.tag)
+}
+
+/// Verify `tag` and decrypt `buffer` in place.
+///
+/// The tag is checked in constant time before anything is decrypted.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidData` if the tag does not
+/// match; `buffer` is left holding the unmodified ciphertext.
+pub fn decrypt_in_place(
+    key: &mut Secret<KEY_LEN>,
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    buffer: &mut [u8],
+    tag: &[u8; TAG_LEN],
+) -> Result<(), MemoryError> {
+    let mut scratch = Scratch::new(key, nonce)?;
+    scratch.verify(aad, buffer, tag)?;
+    scratch.chacha.apply_keystream(buffer);
+    Ok(())
+}
+
+/// Verify `tag` and decrypt `ciphertext` straight into a new
+/// [`SecretBytes`], so the plaintext never exists outside a protected
+/// page.
+///
+/// # Errors
+///
+/// As for [`decrypt_in_place`]; an empty `ciphertext` is rejected with
+/// `InvalidInput`, since a zero-length secret cannot be constructed.
+pub fn decrypt_to_secret(
+    key: &mut Secret<KEY_LEN>,
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8; TAG_LEN],
+) -> Result<SecretBytes, MemoryError> {
+    let mut scratch = Scratch::new(key, nonce)?;
+    scratch.verify(aad, ciphertext, tag)?;
+    SecretBytes::try_new_with(ciphertext.len(), |page| {
+        page.copy_from_slice(ciphertext);
+        scratch.chacha.apply_keystream(page);
+        Ok(())
+    })
+}
+
+/// Per-call cipher state, kept together so it can live in one protected
+/// page.
+struct Scratch {
+    chacha: ChaCha20State,
+    poly: Poly1305,
+    tag: [u8; TAG_LEN],
+}
+
+impl Scratch {
+    /// A read-write protected page with ChaCha20 keyed from `key` and
+    /// positioned at block 1, and Poly1305 keyed from block 0.
+    fn new(key: &mut Secret<KEY_LEN>, nonce: &[u8; NONCE_LEN]) -> Result<Cell<Self>, MemoryError> {
+        let mut cell = Cell::new(Scratch {
+            chacha: ChaCha20State::zeroed(),
+            poly: Poly1305::zeroed(),
+            tag: [0; TAG_LEN],
+        })?;
+        cell.read_write()?;
+        let scratch = &mut *cell;
+        scratch.chacha.init(&*key.read()?, nonce, 0);
+        let block = scratch.chacha.next_block();
+        scratch
+            .poly
+            .init(block[..32].try_into().expect("64-byte block"));
+        Ok(cell)
+    }
+
+    /// Compute the RFC 8439 section 2.8 tag over `aad` and `ciphertext`
+    /// into `self.tag`.
+    fn authenticate(&mut self, aad: &[u8], ciphertext: &[u8]) {
+        self.poly.update(aad);
+        self.poly.pad_to_block();
+        self.poly.update(ciphertext);
+        self.poly.pad_to_block();
+        self.poly.update(&(aad.len() as u64).to_le_bytes());
+        self.poly.update(&(ciphertext.len() as u64).to_le_bytes());
+        self.poly.finish(&mut self.tag);
+    }
+
+    fn verify(
+        &mut self,
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8; TAG_LEN],
+    ) -> Result<(), MemoryError> {
+        self.authenticate(aad, ciphertext);
+        if !ct::eq(&self.tag, tag) {
+            return Err(MemoryError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "authentication tag mismatch",
+            )));
+        }
+        Ok(())
+    }
+}
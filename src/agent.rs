@@ -0,0 +1,614 @@
+//! A local secret agent, in the manner of `ssh-agent`.
+//!
+//! An [`Agent`] holds named secrets in protected pages and serves them over
+//! a Unix domain socket. Every connection is checked against a [`Policy`]
+//! using the peer credentials the kernel reports (`SO_PEERCRED`), and each
+//! secret may carry a time to live after which the agent wipes it. The
+//! agent can be locked with a passphrase, refusing everything but
+//! [`Client::unlock`] and [`Client::wipe_all`] until it is unlocked, and
+//! answers each wrong passphrase more slowly than the last.
+//!
+//! The `memsafe-agent` binary runs an agent and prints the socket path as
+//! `MEMSAFE_AUTH_SOCK` for the shell to export; [`Client::from_env`] picks
+//! it up. A [`Client`] receives secrets from the socket straight into a
+//! locked page with [`Secret::from_reader`] or
+//! [`SecretBytes::from_reader_limited`].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use memsafe::agent::Client;
+//! use memsafe::{Secret, SecretString};
+//!
+//! let agent = Client::from_env().unwrap();
+//! let mut token = SecretString::try_from("ghp_example").unwrap();
+//! agent.add("github", &mut token, Some(Duration::from_secs(3600))).unwrap();
+//!
+//! let mut token: Secret<64> = agent.get("github").unwrap();
+//! # let _ = token.read().unwrap();
+//! ```
+//!
+//! The agent serves one request per connection, one connection at a time,
+//! and gives up on a peer that stalls for [`CONNECTION_TIMEOUT`]. Secrets
+//! cross the socket in the clear: the kernel copies them through socket
+//! buffers, as `ssh-agent` does. Linux only.
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::command::SecretSource;
+use crate::vault::{code_from_kind, kind_from_code, send_reply};
+use crate::{MemoryError, Secret, SecretBytes, ct};
+
+/// Environment variable holding the agent's socket path.
+pub const ENV_VAR: &str = "MEMSAFE_AUTH_SOCK";
+
+/// Longest secret name, in bytes.
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Longest secret or passphrase the agent accepts, in bytes.
+pub const MAX_SECRET_LEN: usize = 64 * 1024;
+
+/// How long the agent waits on a stalled peer before dropping it.
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before refusing a wrong unlock passphrase, multiplied by the
+/// number of failed attempts since the last unlock, up to
+/// [`MAX_UNLOCK_DELAY_STEPS`] times.
+pub const UNLOCK_DELAY: Duration = Duration::from_millis(100);
+
+/// Failed unlock attempts after which the delay stops growing.
+pub const MAX_UNLOCK_DELAY_STEPS: u32 = 10;
+
+const OP_ADD: u8 = 1;
+const OP_GET: u8 = 2;
+const OP_DELETE: u8 = 3;
+const OP_LOCK: u8 = 4;
+const OP_UNLOCK: u8 = 5;
+const OP_WIPE_ALL: u8 = 6;
+
+/// Request header: op, name length, TTL in milliseconds (0 for none), and
+/// the secret length (for `OP_GET`, the longest secret the client takes).
+const HEADER_LEN: usize = 1 + 1 + 8 + 4;
+
+/// Credentials of a connected peer, as reported by `SO_PEERCRED`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerCred {
+    pub pid: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl PeerCred {
+    /// Credentials of the process at the other end of `socket`.
+    pub fn of(socket: &UnixStream) -> Result<PeerCred, MemoryError> {
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(PeerCred {
+            pid: cred.pid as u32,
+            uid: cred.uid,
+            gid: cred.gid,
+        })
+    }
+}
+
+/// Which peers an [`Agent`] serves.
+///
+/// A peer is served if its uid is allowed and, when any pids are allowed,
+/// its pid is one of them. Process ids are reused once a process exits,
+/// so prefer uids; a pid rule suits a long-lived supervisor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policy {
+    uids: Vec<u32>,
+    pids: Vec<u32>,
+}
+
+impl Policy {
+    /// Serve peers running under the agent's own effective uid.
+    pub fn same_user() -> Policy {
+        Policy {
+            uids: vec![unsafe { libc::geteuid() }],
+            pids: Vec::new(),
+        }
+    }
+
+    /// Serve nobody until uids are allowed.
+    pub fn deny_all() -> Policy {
+        Policy {
+            uids: Vec::new(),
+            pids: Vec::new(),
+        }
+    }
+
+    /// Also serve peers running under `uid`.
+    pub fn allow_uid(mut self, uid: u32) -> Self {
+        self.uids.push(uid);
+        self
+    }
+
+    /// Serve only the listed processes, `pid` among them, on top of the
+    /// uid check.
+    pub fn allow_pid(mut self, pid: u32) -> Self {
+        self.pids.push(pid);
+        self
+    }
+
+    /// Whether `peer` is served.
+    pub fn permits(&self, peer: &PeerCred) -> bool {
+        self.uids.contains(&peer.uid) && (self.pids.is_empty() || self.pids.contains(&peer.pid))
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::same_user()
+    }
+}
+
+struct Entry {
+    secret: SecretBytes,
+    expires: Option<Instant>,
+}
+
+/// The serving side: named secrets behind a listening socket.
+pub struct Agent {
+    listener: UnixListener,
+    path: PathBuf,
+    policy: Policy,
+    secrets: HashMap<String, Entry>,
+    /// The passphrase while locked.
+    lock: Option<SecretBytes>,
+    /// Wrong passphrases since the agent was last unlocked.
+    failed_unlocks: u32,
+}
+
+impl Agent {
+    /// Listen on `path`, readable and writable by the owner only.
+    ///
+    /// A stale socket left at `path` by an agent that did not exit cleanly
+    /// is replaced. The socket file is removed when the agent is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `io::Error` from binding wrapped in a `MemoryError`,
+    /// of kind `AddrInUse` if another agent is listening on `path`.
+    pub fn bind(path: impl AsRef<Path>, policy: Policy) -> Result<Agent, MemoryError> {
+        let path = path.as_ref();
+        let listener = match UnixListener::bind(path) {
+            Err(e) if e.kind() == ErrorKind::AddrInUse && is_stale(path) => {
+                std::fs::remove_file(path)?;
+                UnixListener::bind(path)?
+            }
+            bound => bound?,
+        };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Agent {
+            listener,
+            path: path.to_path_buf(),
+            policy,
+            secrets: HashMap::new(),
+            lock: None,
+            failed_unlocks: 0,
+        })
+    }
+
+    /// The socket path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serve connections, wiping secrets as their TTLs run out. Returns
+    /// only if waiting on or accepting from the socket fails; a failing
+    /// connection is dropped and does not stop the agent.
+    pub fn serve(&mut self) -> Result<(), MemoryError> {
+        loop {
+            self.expire();
+            let timeout = match self.secrets.values().filter_map(|e| e.expires).min() {
+                // Round up so the entry has expired on wakeup.
+                Some(at) => {
+                    let ms = at.saturating_duration_since(Instant::now()).as_millis() + 1;
+                    ms.min(libc::c_int::MAX as u128) as libc::c_int
+                }
+                None => -1,
+            };
+            let mut pollfd = libc::pollfd {
+                fd: self.listener.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+                -1 => {
+                    let e = std::io::Error::last_os_error();
+                    if e.kind() != ErrorKind::Interrupted {
+                        return Err(e.into());
+                    }
+                }
+                0 => {}
+                _ => match self.listener.accept() {
+                    Ok((stream, _)) => {
+                        self.expire();
+                        let _ = self.connection(stream);
+                    }
+                    Err(e) if e.kind() == ErrorKind::ConnectionAborted => {}
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                },
+            }
+        }
+    }
+
+    /// Wipe every secret whose TTL has run out.
+    fn expire(&mut self) {
+        let now = Instant::now();
+        self.secrets
+            .retain(|_, entry| entry.expires.is_none_or(|at| at > now));
+    }
+
+    /// Check the peer, then read, handle and answer one request.
+    fn connection(&mut self, stream: UnixStream) -> Result<(), MemoryError> {
+        stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+        stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+        let peer = PeerCred::of(&stream)?;
+        if !self.policy.permits(&peer) {
+            return reply_error(
+                &stream,
+                &error(ErrorKind::PermissionDenied, "peer not allowed by policy"),
+            );
+        }
+        match self.request(&stream) {
+            Ok(false) => send_reply(&stream, 0, &[]),
+            Ok(true) => Ok(()),
+            Err(err) => reply_error(&stream, &err),
+        }
+    }
+
+    /// Handle one request, returning whether it has already replied, as
+    /// `OP_GET` does.
+    fn request(&mut self, stream: &UnixStream) -> Result<bool, MemoryError> {
+        let mut header = [0u8; HEADER_LEN];
+        read_exact(stream, &mut header)?;
+        let op = header[0];
+        let ttl = u64::from_le_bytes(header[2..10].try_into().unwrap());
+        let len = u32::from_le_bytes(header[10..].try_into().unwrap()) as usize;
+        let mut name = [0u8; MAX_NAME_LEN];
+        let name = &mut name[..header[1] as usize];
+        read_exact(stream, name)?;
+        let name = std::str::from_utf8(name).map_err(|_| protocol())?;
+
+        if self.lock.is_some() && !matches!(op, OP_UNLOCK | OP_WIPE_ALL) {
+            return Err(error(ErrorKind::PermissionDenied, "agent is locked"));
+        }
+        match op {
+            OP_ADD => {
+                if name.is_empty() {
+                    return Err(error(ErrorKind::InvalidInput, "empty secret name"));
+                }
+                let secret = read_secret(stream, len)?;
+                let expires = (ttl > 0).then(|| Instant::now() + Duration::from_millis(ttl));
+                self.secrets
+                    .insert(name.to_owned(), Entry { secret, expires });
+            }
+            OP_GET => {
+                let entry = self.secrets.get_mut(name).ok_or_else(unknown_name)?;
+                if entry.secret.len() > len {
+                    return Err(error(
+                        ErrorKind::InvalidInput,
+                        "secret longer than requested",
+                    ));
+                }
+                let mut header = [0u8; 5];
+                header[1..].copy_from_slice(&(entry.secret.len() as u32).to_le_bytes());
+                let mut stream = stream;
+                stream.write_all(&header)?;
+                entry.secret.write_to(&mut stream)?;
+                return Ok(true);
+            }
+            OP_DELETE => {
+                self.secrets.remove(name).ok_or_else(unknown_name)?;
+            }
+            OP_LOCK => self.lock = Some(read_secret(stream, len)?),
+            OP_UNLOCK => {
+                let mut candidate = read_secret(stream, len)?;
+                let lock = self
+                    .lock
+                    .as_mut()
+                    .ok_or_else(|| error(ErrorKind::InvalidInput, "agent is not locked"))?;
+                if !ct::eq(&lock.read()?, &candidate.read()?) {
+                    // Like ssh-agent, stall the whole agent rather than
+                    // this connection, so parallel guesses gain nothing.
+                    self.failed_unlocks = self.failed_unlocks.saturating_add(1);
+                    std::thread::sleep(
+                        UNLOCK_DELAY * self.failed_unlocks.min(MAX_UNLOCK_DELAY_STEPS),
+                    );
+                    return Err(error(ErrorKind::PermissionDenied, "wrong passphrase"));
+                }
+                self.lock = None;
+                self.failed_unlocks = 0;
+            }
+            OP_WIPE_ALL => self.secrets.clear(),
+            _ => return Err(protocol()),
+        }
+        Ok(false)
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Whether `path` is a socket nobody listens on.
+fn is_stale(path: &Path) -> bool {
+    let is_socket = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket());
+    is_socket && UnixStream::connect(path).is_err_and(|e| e.kind() == ErrorKind::ConnectionRefused)
+}
+
+/// Read the `len`-byte secret that follows a request header straight into
+/// a new `SecretBytes`.
+fn read_secret(stream: &UnixStream, len: usize) -> Result<SecretBytes, MemoryError> {
+    if len == 0 || len > MAX_SECRET_LEN {
+        return Err(error(ErrorKind::InvalidInput, "secret length out of range"));
+    }
+    SecretBytes::from_reader_limited(&mut Body::new(stream, len), len)
+}
+
+fn reply_error(stream: &UnixStream, err: &MemoryError) -> Result<(), MemoryError> {
+    let (code, msg) = match err {
+        MemoryError::Io(e) => (code_from_kind(e.kind()), e.to_string()),
+        other => (code_from_kind(ErrorKind::Other), other.to_string()),
+    };
+    send_reply(stream, code, &[msg.as_bytes()])
+}
+
+/// The client side: talks to an agent over its socket, one connection per
+/// call.
+#[derive(Clone, Debug)]
+pub struct Client {
+    path: PathBuf,
+}
+
+impl Client {
+    /// A client for the agent listening on `path`. Nothing is checked
+    /// until the first call.
+    pub fn new(path: impl AsRef<Path>) -> Client {
+        Client {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// A client for the agent named by [`ENV_VAR`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `NotFound` if the variable is unset.
+    pub fn from_env() -> Result<Client, MemoryError> {
+        std::env::var_os(ENV_VAR)
+            .map(Client::new)
+            .ok_or_else(|| error(ErrorKind::NotFound, "MEMSAFE_AUTH_SOCK is not set"))
+    }
+
+    /// Store `secret` under `name`, replacing any secret of that name. With
+    /// a `ttl`, the agent wipes it once that much time has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidInput` if `name` is empty or
+    /// longer than [`MAX_NAME_LEN`], or the secret is longer than
+    /// [`MAX_SECRET_LEN`]; `PermissionDenied` if the policy refuses this
+    /// process or the agent is locked; or the connection's error.
+    pub fn add<S: SecretSource>(
+        &self,
+        name: &str,
+        secret: &mut S,
+        ttl: Option<Duration>,
+    ) -> Result<(), MemoryError> {
+        // A zero TTL still expires: the agent treats 0 as none.
+        let ttl = ttl.map_or(0, |ttl| ttl.as_millis().clamp(1, u64::MAX as u128) as u64);
+        let len = secret.secret_len();
+        let socket = self.request(OP_ADD, name, ttl, len, Some(secret))?;
+        expect_empty(&socket)
+    }
+
+    /// Fetch the secret stored under `name` into a new secret of at most
+    /// `N` bytes, zero-padded, reading the socket straight into the locked
+    /// page.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `NotFound` if there is no such
+    /// secret (or it has expired), `InvalidInput` if it is longer than `N`
+    /// bytes, `PermissionDenied` as for [`Client::add`], or the
+    /// connection's error.
+    pub fn get<const N: usize>(&self, name: &str) -> Result<Secret<N>, MemoryError> {
+        let socket = self.request(OP_GET, name, 0, N, None)?;
+        let len = reply_header(&socket)??;
+        if len > N {
+            return Err(protocol());
+        }
+        Secret::from_reader(&mut Body::new(&socket, len))
+    }
+
+    /// Fetch the secret stored under `name`, of at most `max` bytes, into
+    /// a new `SecretBytes` of its exact length.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Client::get`], with `max` in place of `N`.
+    pub fn get_bytes(&self, name: &str, max: usize) -> Result<SecretBytes, MemoryError> {
+        let max = max.min(MAX_SECRET_LEN);
+        let socket = self.request(OP_GET, name, 0, max, None)?;
+        let len = reply_header(&socket)??;
+        if len == 0 || len > max {
+            return Err(protocol());
+        }
+        SecretBytes::from_reader_limited(&mut Body::new(&socket, len), len)
+    }
+
+    /// Wipe and forget the secret stored under `name`.
+    pub fn delete(&self, name: &str) -> Result<(), MemoryError> {
+        let socket = self.request(OP_DELETE, name, 0, 0, None)?;
+        expect_empty(&socket)
+    }
+
+    /// Lock the agent with `passphrase`. A locked agent refuses every
+    /// request but [`Client::unlock`] and [`Client::wipe_all`].
+    pub fn lock<S: SecretSource>(&self, passphrase: &mut S) -> Result<(), MemoryError> {
+        let len = passphrase.secret_len();
+        let socket = self.request(OP_LOCK, "", 0, len, Some(passphrase))?;
+        expect_empty(&socket)
+    }
+
+    /// Unlock the agent. The passphrase is compared in constant time.
+    ///
+    /// A wrong passphrase is refused after [`UNLOCK_DELAY`] times the
+    /// number of failures since the last unlock, during which the agent
+    /// serves no one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `PermissionDenied` if `passphrase`
+    /// is wrong, or `InvalidInput` if the agent is not locked.
+    pub fn unlock<S: SecretSource>(&self, passphrase: &mut S) -> Result<(), MemoryError> {
+        let len = passphrase.secret_len();
+        let socket = self.request(OP_UNLOCK, "", 0, len, Some(passphrase))?;
+        expect_empty(&socket)
+    }
+
+    /// Wipe every secret the agent holds, locked or not. A lock stays in
+    /// place.
+    pub fn wipe_all(&self) -> Result<(), MemoryError> {
+        let socket = self.request(OP_WIPE_ALL, "", 0, 0, None)?;
+        expect_empty(&socket)
+    }
+
+    /// Connect and send one request, followed by `secret` if given.
+    fn request(
+        &self,
+        op: u8,
+        name: &str,
+        ttl: u64,
+        len: usize,
+        secret: Option<&mut dyn SecretSource>,
+    ) -> Result<UnixStream, MemoryError> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(error(ErrorKind::InvalidInput, "secret name too long"));
+        }
+        if secret.is_some() && len > MAX_SECRET_LEN {
+            return Err(error(ErrorKind::InvalidInput, "secret too long"));
+        }
+        let socket = UnixStream::connect(&self.path)?;
+        let mut header = [0u8; HEADER_LEN];
+        header[0] = op;
+        header[1] = name.len() as u8;
+        header[2..10].copy_from_slice(&ttl.to_le_bytes());
+        header[10..].copy_from_slice(&(len.min(u32::MAX as usize) as u32).to_le_bytes());
+        let sent = (|| {
+            (&socket).write_all(&header)?;
+            (&socket).write_all(name.as_bytes())?;
+            match secret {
+                Some(secret) => secret.write_to_pipe(socket.as_raw_fd()),
+                None => Ok(()),
+            }
+        })();
+        match sent {
+            Ok(()) => Ok(socket),
+            Err(e) => match reply_header(&socket) {
+                // The agent refused the request before reading all of it.
+                Ok(Err(refused)) => Err(refused),
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+/// Read a reply header and return the body length, or the agent's error.
+/// The outer error is the connection's.
+fn reply_header(socket: &UnixStream) -> Result<Result<usize, MemoryError>, MemoryError> {
+    let mut header = [0u8; 5];
+    read_exact(socket, &mut header)?;
+    let len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+    if header[0] == 0 {
+        return Ok(Ok(len));
+    }
+    if len > MAX_SECRET_LEN {
+        return Err(protocol());
+    }
+    let mut msg = vec![0u8; len];
+    read_exact(socket, &mut msg)?;
+    Ok(Err(MemoryError::from(std::io::Error::new(
+        kind_from_code(header[0]),
+        String::from_utf8_lossy(&msg).into_owned(),
+    ))))
+}
+
+fn expect_empty(socket: &UnixStream) -> Result<(), MemoryError> {
+    match reply_header(socket)?? {
+        0 => Ok(()),
+        _ => Err(protocol()),
+    }
+}
+
+/// The next `left` bytes of a socket; ending early is `UnexpectedEof`
+/// rather than a short read, so a truncated secret is not zero-padded.
+struct Body<'a> {
+    socket: &'a UnixStream,
+    left: usize,
+}
+
+impl<'a> Body<'a> {
+    fn new(socket: &'a UnixStream, len: usize) -> Self {
+        Body { socket, left: len }
+    }
+}
+
+impl Read for Body<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.left == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let max = buf.len().min(self.left);
+        let n = (&mut self.socket).read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        self.left -= n;
+        Ok(n)
+    }
+}
+
+fn read_exact(mut socket: &UnixStream, buf: &mut [u8]) -> Result<(), MemoryError> {
+    socket.read_exact(buf).map_err(MemoryError::from)
+}
+
+fn unknown_name() -> MemoryError {
+    error(ErrorKind::NotFound, "no such secret")
+}
+
+fn protocol() -> MemoryError {
+    error(ErrorKind::InvalidData, "agent protocol violation")
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
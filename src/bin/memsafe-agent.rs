@@ -0,0 +1,83 @@
+//! `memsafe-agent`: hold secrets in protected memory and serve them over a
+//! Unix socket. See `memsafe::agent`.
+//!
+//! ```text
+//! memsafe-agent [--socket PATH] [--allow-uid UID]... [--allow-pid PID]...
+//! ```
+//!
+//! The socket defaults to `$XDG_RUNTIME_DIR/memsafe-agent.sock`. The agent
+//! prints a `MEMSAFE_AUTH_SOCK=...; export MEMSAFE_AUTH_SOCK;` line for
+//! the shell, then serves in the foreground until killed; run it under a
+//! service manager or with `&`.
+
+#[cfg(target_os = "linux")]
+fn main() -> std::process::ExitCode {
+    use std::io::Write;
+    use std::process::ExitCode;
+
+    use memsafe::agent::{Agent, ENV_VAR, Policy};
+
+    const USAGE: &str =
+        "usage: memsafe-agent [--socket PATH] [--allow-uid UID]... [--allow-pid PID]...";
+
+    let mut socket = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| std::path::PathBuf::from(dir).join("memsafe-agent.sock"));
+    let mut policy = Policy::same_user();
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        let id = value
+            .as_ref()
+            .and_then(|v| v.to_str())
+            .and_then(|v| v.parse::<u32>().ok());
+        match (arg.to_str(), value, id) {
+            (Some("--socket"), Some(path), _) => socket = Some(path.into()),
+            (Some("--allow-uid"), _, Some(uid)) => policy = policy.allow_uid(uid),
+            (Some("--allow-pid"), _, Some(pid)) => policy = policy.allow_pid(pid),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let Some(socket) = socket else {
+        eprintln!("memsafe-agent: XDG_RUNTIME_DIR is not set; pass --socket");
+        return ExitCode::from(2);
+    };
+
+    // Keep other processes of this user from attaching or reading our
+    // memory through /proc, and out of core dumps.
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        eprintln!("memsafe-agent: prctl: {}", std::io::Error::last_os_error());
+        return ExitCode::FAILURE;
+    }
+
+    let mut agent = match Agent::bind(&socket, policy) {
+        Ok(agent) => agent,
+        Err(e) => {
+            eprintln!("memsafe-agent: {}: {e}", socket.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut stdout = std::io::stdout();
+    let _ = writeln!(
+        stdout,
+        "{ENV_VAR}={}; export {ENV_VAR};",
+        agent.path().display()
+    );
+    let _ = stdout.flush();
+
+    match agent.serve() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("memsafe-agent: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() -> std::process::ExitCode {
+    eprintln!("memsafe-agent: only supported on Linux");
+    std::process::ExitCode::FAILURE
+}
@@ -0,0 +1,114 @@
+//! Capability-gated secrets.
+//!
+//! A plain [`Secret`] can be read by anything holding `&mut` to it. In a
+//! larger program that is often too coarse: the module that *stores* a
+//! credential is not necessarily the one that should *see* it. A
+//! [`CapSecret`] is built together with two unforgeable tokens, a
+//! [`ReadCap`] and a [`WriteCap`], and its `read()` / `write()` only open the
+//! page when the matching token is presented.
+//!
+//! The tokens cannot be constructed outside this module, are not `Clone`,
+//! and carry the id of the secret they were minted for, so a token for one
+//! secret never opens another. Ownership of the right to see the plaintext
+//! can therefore be handed around separately from ownership of the
+//! container:
+//!
+//! ```
+//! use memsafe::capability::CapSecret;
+//!
+//! let (mut secret, read_cap, _write_cap) =
+//!     CapSecret::<32>::new_with(|buf| buf[..6].copy_from_slice(b"s3cr3t")).unwrap();
+//!
+//! // Whoever owns `read_cap` can look; whoever owns only `secret` cannot.
+//! let view = secret.read(&read_cap).unwrap();
+//! assert_eq!(&view[..6], b"s3cr3t");
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{MemSafeRead, MemSafeWrite, MemoryError, Secret};
+
+/// Source of per-secret ids. Starts at 1 so that a zeroed id is never valid.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A [`Secret`] whose access guards require a capability token.
+pub struct CapSecret<const N: usize> {
+    secret: Secret<N>,
+    id: u64,
+}
+
+/// The right to obtain a read guard on one specific [`CapSecret`].
+///
+/// Deliberately neither `Clone` nor `Debug`: a capability can be moved or
+/// lent, never duplicated or printed.
+///
+/// ```compile_fail
+/// use memsafe::capability::CapSecret;
+/// let (_secret, read_cap, _) = CapSecret::<8>::new_with(|_| {}).unwrap();
+/// let copy = read_cap.clone(); // does not compile: `ReadCap` is not `Clone`
+/// ```
+pub struct ReadCap {
+    id: u64,
+}
+
+/// The right to obtain a write guard on one specific [`CapSecret`].
+///
+/// Deliberately neither `Clone` nor `Debug`, like [`ReadCap`].
+pub struct WriteCap {
+    id: u64,
+}
+
+impl<const N: usize> CapSecret<N> {
+    /// Allocate an `N`-byte secret, fill it in place, and mint its
+    /// capabilities. See [`Secret::new_with`] for the construction
+    /// guarantees.
+    pub fn new_with<F>(init: F) -> Result<(Self, ReadCap, WriteCap), MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]),
+    {
+        Secret::new_with(init).map(Self::from_secret)
+    }
+
+    /// Move an existing secret behind capability checks. Anyone who kept a
+    /// reference to `secret` loses it here, so from now on only the returned
+    /// tokens can open the page.
+    pub fn from_secret(secret: Secret<N>) -> (Self, ReadCap, WriteCap) {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        (CapSecret { secret, id }, ReadCap { id }, WriteCap { id })
+    }
+
+    /// Obtain temporary read access, provided `cap` was minted for this
+    /// secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `PermissionDenied` if `cap` belongs to
+    /// a different secret, or any error from [`Secret::read`].
+    pub fn read(&mut self, cap: &ReadCap) -> Result<MemSafeRead<'_, [u8; N]>, MemoryError> {
+        self.check(cap.id)?;
+        self.secret.read()
+    }
+
+    /// Obtain temporary read-write access, provided `cap` was minted for
+    /// this secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `PermissionDenied` if `cap` belongs to
+    /// a different secret, or any error from [`Secret::write`].
+    pub fn write(&mut self, cap: &WriteCap) -> Result<MemSafeWrite<'_, [u8; N]>, MemoryError> {
+        self.check(cap.id)?;
+        self.secret.write()
+    }
+
+    fn check(&self, id: u64) -> Result<(), MemoryError> {
+        if id == self.id { Ok(()) } else { Err(denied()) }
+    }
+}
+
+fn denied() -> MemoryError {
+    MemoryError::from(std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "capability was not minted for this secret",
+    ))
+}
@@ -9,14 +9,21 @@ use crate::ffi::{mem_no_dump, mem_wipe_on_fork};
 use crate::{
     MemoryError,
     ffi::{mem_alloc, mem_dealloc, mem_lock, mem_readonly, mem_readwrite, mem_unlock},
-    ptr_ops::{ptr_deref, ptr_deref_mut, ptr_drop_in_place, ptr_fill_zero, secure_zero},
+    ptr_ops::{
+        ptr_deref, ptr_deref_mut, ptr_drop_in_place, ptr_fill_zero, ptr_fill_zero_bytes,
+        secure_zero,
+    },
 };
 
 // No `Debug`: this crate withholds `Debug` from every type that participates
 // in handling secret memory, so nothing about the page (not even its address)
 // can leak through a formatting macro.
-pub struct Cell<T> {
+//
+// `len` is the size of the mapping in bytes: `size_of::<T>()` for sized
+// values, the slice length for `Cell<[u8]>`.
+pub struct Cell<T: ?Sized> {
     ptr: *mut T,
+    len: usize,
 }
 
 /// Tracks how far `Cell` construction has progressed. Construction is strictly
@@ -51,13 +58,13 @@ enum PartialState {
 /// failed by the time we get here, and amplifying that failure into a
 /// panic-during-drop (which aborts the process if a panic is already
 /// unwinding) serves no one.
-struct PartialCell<T> {
+struct PartialCell<T: ?Sized> {
     ptr: *mut T,
     len: usize,
     state: PartialState,
 }
 
-impl<T> PartialCell<T> {
+impl<T: ?Sized> PartialCell<T> {
     fn new(ptr: *mut T, len: usize) -> Self {
         Self {
             ptr,
@@ -85,8 +92,9 @@ impl<T> PartialCell<T> {
     }
 }
 
-impl<T> Drop for PartialCell<T> {
+impl<T: ?Sized> Drop for PartialCell<T> {
     fn drop(&mut self) {
+        let raw = self.ptr as *mut u8;
         // If a `T` has been written into the page, drop it and zero the
         // bytes before unmapping. The page is RW at this point in the
         // construction sequence (we haven't yet called the final mprotect
@@ -94,32 +102,36 @@ impl<T> Drop for PartialCell<T> {
         // pathological case where some intermediate step left the page
         // in a non-RW state.
         if self.state == PartialState::Written {
-            let _ = mem_readwrite(self.ptr, self.len);
+            let _ = mem_readwrite(raw, self.len);
             // `*self.ptr` holds a valid `T` written by `Cell::new`'s
             // byte copy or `Cell::new_with`'s `init` closure. The page
             // is RW (or has just been re-set RW above).
             ptr_drop_in_place(self.ptr);
-            ptr_fill_zero(self.ptr);
+            ptr_fill_zero_bytes(self.ptr, self.len);
         }
         // Only `munlock` if we successfully locked — `munlock` on
         // never-locked memory is documented as UB on some platforms.
         if matches!(self.state, PartialState::Locked | PartialState::Written) {
-            let _ = mem_unlock(self.ptr, self.len);
+            let _ = mem_unlock(raw, self.len);
         }
         // The mapping always exists in this state — `Allocated` is the
         // entry condition for constructing a `PartialCell`.
-        let _ = mem_dealloc(self.ptr, self.len);
+        let _ = mem_dealloc(raw, self.len);
     }
 }
 
+fn zero_sized_error() -> MemoryError {
+    MemoryError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "zero-sized values cannot be placed in protected memory",
+    ))
+}
+
 impl<T> Cell<T> {
     pub fn new(mut value: T) -> Result<Cell<T>, MemoryError> {
         let len = std::mem::size_of::<T>();
         if len == 0 {
-            return Err(MemoryError::from(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "zero-sized values cannot be placed in protected memory",
-            )));
+            return Err(zero_sized_error());
         }
         let ptr = mem_alloc(len)?;
         // From here on the page is owned by `guard`. Any `?` failure or
@@ -156,6 +168,58 @@ impl<T> Cell<T> {
 
         Ok(Cell {
             ptr: guard.disarm(),
+            len,
+        })
+    }
+}
+
+impl<T: ?Sized> Cell<T> {
+    /// The construction sequence shared by the in-place constructors.
+    ///
+    /// Maps `len` bytes, locks them, applies the Linux `madvise` flags, and
+    /// hands the OS-zeroed page to `init` as a `&mut T` built by `cast`.
+    /// An `Err` from `init`, like a panic, rolls back through
+    /// `PartialCell::drop`, wiping whatever `init` had written.
+    fn alloc_with<F>(
+        len: usize,
+        cast: impl FnOnce(*mut u8) -> *mut T,
+        init: F,
+    ) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut T) -> Result<(), MemoryError>,
+    {
+        if len == 0 {
+            return Err(zero_sized_error());
+        }
+        let raw: *mut u8 = mem_alloc(len)?;
+        let ptr = cast(raw);
+        // From here on the page is owned by `guard`. Any `?` failure, or
+        // a panic from `init`, will roll back through `PartialCell::drop`.
+        let mut guard = PartialCell::new(ptr, len);
+
+        mem_lock(raw, len)?;
+        guard.mark_locked();
+
+        #[cfg(target_os = "linux")]
+        mem_no_dump(raw, len)?;
+        #[cfg(target_os = "linux")]
+        mem_wipe_on_fork(raw, len)?;
+
+        // Mark `Written` *before* invoking `init`: the closure may write
+        // partial secret bytes and then panic or fail. Byte buffers have
+        // trivial `Drop`, so the guard's `drop_in_place` is a no-op; the
+        // important effect is the volatile page-wipe on rollback.
+        guard.mark_written();
+        init(unsafe { &mut *ptr })?;
+
+        #[cfg(windows)]
+        mem_readonly(raw, len)?;
+        #[cfg(unix)]
+        mem_noaccess(raw, len)?;
+
+        Ok(Cell {
+            ptr: guard.disarm(),
+            len,
         })
     }
 
@@ -173,15 +237,61 @@ impl<T> Cell<T> {
 
     #[cfg(unix)]
     pub fn no_access(&mut self) -> Result<(), MemoryError> {
-        mem_noaccess(self.ptr, std::mem::size_of::<T>())
+        mem_noaccess(self.ptr as *mut u8, self.len)
     }
 
     pub fn read_only(&mut self) -> Result<(), MemoryError> {
-        mem_readonly(self.ptr, std::mem::size_of::<T>())
+        mem_readonly(self.ptr as *mut u8, self.len)
     }
 
     pub fn read_write(&mut self) -> Result<(), MemoryError> {
-        mem_readwrite(self.ptr, std::mem::size_of::<T>())
+        mem_readwrite(self.ptr as *mut u8, self.len)
+    }
+
+    /// Run `f` on the value through a shared reference, with the page
+    /// readable only for the duration of the call.
+    ///
+    /// The `MemSafe` guards need `&mut self`; this serves callers that only
+    /// get `&self`, such as a `serde` `serialize_with` function. It is sound
+    /// because `Cell` is `!Sync` and every guard borrows its cell mutably:
+    /// while `&self` is live, no guard exists and no other thread can reach
+    /// the page. A nested `peek` of the same cell from inside `f` leaves
+    /// the protection to the outermost call.
+    #[cfg(feature = "serde")]
+    pub(crate) fn peek<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, MemoryError> {
+        let raw = self.ptr as *mut u8;
+        if PEEKED.with_borrow(|open| open.contains(&(raw as usize))) {
+            return Ok(f(ptr_deref(self.ptr)));
+        }
+        mem_readonly(raw, self.len)?;
+        PEEKED.with_borrow_mut(|open| open.push(raw as usize));
+        let _reseal = Peek { raw, len: self.len };
+        Ok(f(ptr_deref(self.ptr)))
+    }
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    /// Addresses of the pages `Cell::peek` currently holds open on this
+    /// thread.
+    static PEEKED: std::cell::RefCell<Vec<usize>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Closes a page opened by `Cell::peek`, also when `f` unwinds.
+#[cfg(feature = "serde")]
+struct Peek {
+    raw: *mut u8,
+    len: usize,
+}
+
+#[cfg(feature = "serde")]
+impl Drop for Peek {
+    fn drop(&mut self) {
+        PEEKED.with_borrow_mut(|open| open.retain(|&p| p != self.raw as usize));
+        #[cfg(unix)]
+        mem_noaccess(self.raw, self.len).unwrap();
+        #[cfg(windows)]
+        let _ = self.len;
     }
 }
 
@@ -202,43 +312,21 @@ impl<const N: usize> Cell<[u8; N]> {
     where
         F: FnOnce(&mut [u8; N]),
     {
-        let len = std::mem::size_of::<[u8; N]>();
-        if len == 0 {
-            return Err(MemoryError::from(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "zero-sized values cannot be placed in protected memory",
-            )));
-        }
-        let ptr: *mut [u8; N] = mem_alloc(len)?;
-        // From here on the page is owned by `guard`. Any `?` failure, or
-        // a panic from `init`, will roll back through `PartialCell::drop`.
-        let mut guard = PartialCell::new(ptr, len);
-
-        mem_lock(ptr, len)?;
-        guard.mark_locked();
-
-        #[cfg(target_os = "linux")]
-        mem_no_dump(ptr, len)?;
-        #[cfg(target_os = "linux")]
-        mem_wipe_on_fork(ptr, len)?;
-
-        // Mark `Written` *before* invoking `init`: the closure may write
-        // partial secret bytes and then panic. `[u8; N]` has trivial
-        // `Drop`, so the guard's `drop_in_place` is a no-op; the
-        // important effect is the volatile page-wipe on rollback.
-        guard.mark_written();
-        init(unsafe { &mut *ptr });
-
-        #[cfg(windows)]
-        mem_readonly(ptr, len)?;
-        #[cfg(unix)]
-        mem_noaccess(ptr, len)?;
-
-        Ok(Cell {
-            ptr: guard.disarm(),
+        Self::try_new_with(|page| {
+            init(page);
+            Ok(())
         })
     }
 
+    /// Like [`Cell::new_with`], but `init` may fail. On `Err` the page is
+    /// wiped and released exactly as on a panic, and the error is returned.
+    pub fn try_new_with<F>(init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]) -> Result<(), MemoryError>,
+    {
+        Self::alloc_with(N, |raw| raw as *mut [u8; N], init)
+    }
+
     /// Encapsulate an owned byte source into a fresh protected page,
     /// volatile-zeroing the source after the copy.
     ///
@@ -267,7 +355,35 @@ impl<const N: usize> Cell<[u8; N]> {
     }
 }
 
-impl<T> Deref for Cell<T> {
+impl Cell<[u8]> {
+    /// Allocate a protected page of `len` bytes and let `init` fill it in
+    /// place. The runtime-length counterpart of
+    /// [`Cell::<[u8; N]>::try_new_with`], with the same guarantees.
+    pub fn try_new_slice_with<F>(len: usize, init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<(), MemoryError>,
+    {
+        Self::alloc_with(
+            len,
+            |raw| std::ptr::slice_from_raw_parts_mut(raw, len),
+            init,
+        )
+    }
+
+    /// Reinterpret the page as `str`. The caller must have validated the
+    /// contents as UTF-8, and every later write must preserve that.
+    pub fn into_str_unchecked(self) -> Cell<str> {
+        let cell = Cell {
+            ptr: self.ptr as *mut str,
+            len: self.len,
+        };
+        // Ownership of the page moves to `cell`.
+        std::mem::forget(self);
+        cell
+    }
+}
+
+impl<T: ?Sized> Deref for Cell<T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -275,26 +391,26 @@ impl<T> Deref for Cell<T> {
     }
 }
 
-impl<T> DerefMut for Cell<T> {
+impl<T: ?Sized> DerefMut for Cell<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         ptr_deref_mut(self.ptr)
     }
 }
 
-impl<T> Drop for Cell<T> {
+impl<T: ?Sized> Drop for Cell<T> {
     fn drop(&mut self) {
-        let len = std::mem::size_of::<T>();
+        let raw = self.ptr as *mut u8;
         // Fail secure: if the page can't be made writable it can't be wiped,
         // so leak it — still mapped, locked, and sealed — rather than return
         // a dirty page to the OS for reuse by the next allocation. This also
         // keeps drop from panicking, which would abort the process when a
         // panic is already unwinding.
-        if mem_readwrite(self.ptr, len).is_err() {
+        if mem_readwrite(raw, self.len).is_err() {
             return;
         }
         ptr_drop_in_place(self.ptr);
-        ptr_fill_zero(self.ptr);
-        let _ = mem_unlock(self.ptr, len);
-        let _ = mem_dealloc(self.ptr, len);
+        ptr_fill_zero_bytes(self.ptr, self.len);
+        let _ = mem_unlock(raw, self.len);
+        let _ = mem_dealloc(raw, self.len);
     }
 }
@@ -0,0 +1,482 @@
+//! Exchanging secrets with child processes.
+//!
+//! Secret managers are often reached through a command: `pass show`,
+//! `op read`, `vault kv get -field=...`, or a site-specific script.
+//! `Command::output()` collects stdout in a heap `Vec` that is freed
+//! unwiped. [`SecretCommand`] instead spawns the child with stdout on a
+//! pipe and reads the pipe straight into a locked page.
+//!
+//! ```
+//! use std::process::Command;
+//! use std::time::Duration;
+//! use memsafe::command::SecretCommand;
+//!
+//! let mut command = Command::new("echo");
+//! command.arg("s3cr3t");
+//! let mut token = SecretCommand::new(command)
+//!     .trim_newline()
+//!     .timeout(Duration::from_secs(10))
+//!     .read_bytes(4096)
+//!     .unwrap();
+//! assert_eq!(&*token.read().unwrap(), b"s3cr3t");
+//! ```
+//!
+//! The child's stdin and stderr are left as configured on the `Command`.
+//! If the child fails, runs out of time or writes too much, it is killed
+//! and reaped, and every page written so far is wiped.
+//!
+//! In the other direction, [`SecretCommandExt`] hands a secret to a child
+//! over a pipe instead of through argv or the environment, which other
+//! users can read in `/proc/<pid>/cmdline` and `/proc/<pid>/environ`:
+//!
+//! ```
+//! use std::process::{Command, Stdio};
+//! use memsafe::Secret;
+//! use memsafe::command::SecretCommandExt;
+//!
+//! let mut password = Secret::<7>::new_with(|b| b.copy_from_slice(b"hunter2")).unwrap();
+//! let mut command = Command::new("cat");
+//! command.stdout(Stdio::piped());
+//! let child = command.secret_stdin(&mut password).spawn().unwrap();
+//! assert_eq!(child.wait_with_output().unwrap().stdout, b"hunter2");
+//! ```
+
+use std::io::{ErrorKind, Read};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::cell::Cell;
+use crate::ptr_ops::secure_zero;
+use crate::{MemoryError, Secret, SecretBytes, SecretString};
+
+/// Runs a command and captures its stdout as a secret. Built with
+/// [`SecretCommand::new`], configured with its setters, and consumed by
+/// [`SecretCommand::read`] or [`SecretCommand::read_bytes`].
+pub struct SecretCommand {
+    command: Command,
+    trim: bool,
+    timeout: Option<Duration>,
+}
+
+impl SecretCommand {
+    /// Capture `command`'s stdout verbatim, with no time limit.
+    pub fn new(command: Command) -> Self {
+        SecretCommand {
+            command,
+            trim: false,
+            timeout: None,
+        }
+    }
+
+    /// Drop one trailing `\n` or `\r\n` from the output. It does not count
+    /// towards the size limit.
+    pub fn trim_newline(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    /// Kill the child and fail if it has not exited `timeout` after being
+    /// spawned.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run the command and capture at most `N` bytes of output into a new
+    /// secret, zero-padded.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `io::Error` from spawning or reading wrapped in a
+    /// `MemoryError`, or a `MemoryError` of kind:
+    /// - `InvalidInput` if the output exceeds the limit;
+    /// - `TimedOut` if the timeout expires;
+    /// - `Other` if the command exits unsuccessfully.
+    pub fn read<const N: usize>(self) -> Result<Secret<N>, MemoryError> {
+        let (mut staging, len) = self.run(N)?;
+        staging.read_only()?;
+        Secret::new_with(|page| page[..len].copy_from_slice(&staging[..len]))
+    }
+
+    /// Run the command and capture at most `max` bytes of output into a
+    /// new [`SecretBytes`] of the output's length.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SecretCommand::read`]; empty output is rejected with
+    /// `InvalidInput`, since a zero-length secret cannot be constructed.
+    pub fn read_bytes(self, max: usize) -> Result<SecretBytes, MemoryError> {
+        let (mut staging, len) = self.run(max)?;
+        staging.read_only()?;
+        SecretBytes::new_with(len, |page| page.copy_from_slice(&staging[..len]))
+    }
+
+    /// Run the child and return its output, trimmed, in a sealed staging
+    /// page with room for `max` bytes and a `\r\n`.
+    fn run(mut self, max: usize) -> Result<(Cell<[u8]>, usize), MemoryError> {
+        let page_len = max
+            .checked_add(2)
+            .ok_or_else(|| error(ErrorKind::InvalidInput, "size limit too large"))?;
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut child = Running(self.command.stdout(Stdio::piped()).spawn()?);
+        let mut stdout = child.0.stdout.take().expect("stdout is piped");
+
+        let mut len = 0;
+        let staging = Cell::<[u8]>::try_new_slice_with(page_len, |page| {
+            len = read_output(&mut stdout, page, deadline)?;
+            if self.trim && page[..len].ends_with(b"\n") {
+                len -= 1;
+                if page[..len].ends_with(b"\r") {
+                    len -= 1;
+                }
+            }
+            if len > max {
+                return Err(error(ErrorKind::InvalidInput, "output exceeds size limit"));
+            }
+            Ok(())
+        })?;
+        drop(stdout);
+
+        let status = child.wait(deadline)?;
+        if !status.success() {
+            return Err(MemoryError::from(std::io::Error::other(format!(
+                "secret command failed: {status}"
+            ))));
+        }
+        Ok((staging, len))
+    }
+}
+
+/// A spawned child, killed and reaped on drop unless it already exited.
+struct Running(Child);
+
+impl Running {
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<std::process::ExitStatus, MemoryError> {
+        let Some(deadline) = deadline else {
+            return Ok(self.0.wait()?);
+        };
+        // The child may close stdout and keep running; poll its status.
+        loop {
+            if let Some(status) = self.0.try_wait()? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                return Err(timed_out());
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+        }
+        let _ = self.0.wait();
+    }
+}
+
+/// Read `stdout` until end of input into `page` and return the number of
+/// bytes read. If the page fills up, one more byte is read into a
+/// wiped-after-use probe to tell a full page from an overlong output.
+fn read_output(
+    stdout: &mut ChildStdout,
+    page: &mut [u8],
+    deadline: Option<Instant>,
+) -> Result<usize, MemoryError> {
+    let mut len = 0;
+    loop {
+        wait_readable(stdout, deadline)?;
+        let mut probe = [0u8; 1];
+        let buf = if len < page.len() {
+            &mut page[len..]
+        } else {
+            &mut probe[..]
+        };
+        let result = stdout.read(buf);
+        secure_zero(&mut probe);
+        match result {
+            Ok(0) => return Ok(len),
+            Ok(_) if len == page.len() => {
+                return Err(error(ErrorKind::InvalidInput, "output exceeds size limit"));
+            }
+            Ok(n) => len += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Block until `stdout` is readable or closed, or fail at `deadline`.
+fn wait_readable(stdout: &ChildStdout, deadline: Option<Instant>) -> Result<(), MemoryError> {
+    let mut poll = libc::pollfd {
+        fd: stdout.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        let timeout_ms = match deadline {
+            None => -1,
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                // Round up, so the poll never ends just before the deadline.
+                left.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32
+            }
+        };
+        match unsafe { libc::poll(&mut poll, 1, timeout_ms) } {
+            0 => return Err(timed_out()),
+            n if n > 0 => return Ok(()),
+            _ => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+}
+
+/// Feeding secrets to a child process through pipes, as an extension of
+/// [`std::process::Command`].
+///
+/// Each call returns a [`SecretSpawn`] that collects further secrets and
+/// finally spawns the child. The pipes are created at spawn time; the
+/// child inherits only their read ends, and the parent writes each secret
+/// from its page with `write(2)`, then closes its end so the child sees
+/// end of input.
+pub trait SecretCommandExt {
+    /// Feed `secret` to the child's stdin. The stdin configured on the
+    /// command is replaced, and reset to `Stdio::null()` after the spawn.
+    fn secret_stdin<'a, S: SecretSource>(&'a mut self, secret: &'a mut S) -> SecretSpawn<'a>;
+
+    /// Feed `secret` to the child on descriptor `fd`, which must be 3 or
+    /// higher; use [`SecretCommandExt::secret_stdin`] for stdin.
+    fn secret_fd<'a, S: SecretSource>(
+        &'a mut self,
+        fd: RawFd,
+        secret: &'a mut S,
+    ) -> SecretSpawn<'a>;
+}
+
+impl SecretCommandExt for Command {
+    fn secret_stdin<'a, S: SecretSource>(&'a mut self, secret: &'a mut S) -> SecretSpawn<'a> {
+        SecretSpawn {
+            command: self,
+            feeds: Vec::new(),
+        }
+        .secret_stdin(secret)
+    }
+
+    fn secret_fd<'a, S: SecretSource>(
+        &'a mut self,
+        fd: RawFd,
+        secret: &'a mut S,
+    ) -> SecretSpawn<'a> {
+        SecretSpawn {
+            command: self,
+            feeds: Vec::new(),
+        }
+        .secret_fd(fd, secret)
+    }
+}
+
+/// A [`Command`] with secrets to feed to the child. Created by
+/// [`SecretCommandExt`].
+pub struct SecretSpawn<'a> {
+    command: &'a mut Command,
+    /// Target descriptor in the child, and the secret to write to it.
+    feeds: Vec<(RawFd, &'a mut dyn SecretSource)>,
+}
+
+impl<'a> SecretSpawn<'a> {
+    /// Also feed `secret` to the child's stdin.
+    pub fn secret_stdin<S: SecretSource>(mut self, secret: &'a mut S) -> Self {
+        self.feeds.push((libc::STDIN_FILENO, secret));
+        self
+    }
+
+    /// Also feed `secret` to the child on descriptor `fd`.
+    pub fn secret_fd<S: SecretSource>(mut self, fd: RawFd, secret: &'a mut S) -> Self {
+        self.feeds.push((fd, secret));
+        self
+    }
+
+    /// Spawn the child and write every secret to its pipe, in the order
+    /// they were added.
+    ///
+    /// A write blocks while its pipe is full, so a secret larger than the
+    /// pipe capacity (64 KiB on Linux) is only fully written once the child
+    /// reads it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidInput` if a target
+    /// descriptor is below 3 (other than stdin) or used twice, and the
+    /// `io::Error` from creating the pipes or spawning wrapped in a
+    /// `MemoryError`. If writing a secret fails, for example because the
+    /// child exited without reading it, the child is killed and reaped and
+    /// the error is returned.
+    pub fn spawn(self) -> Result<Child, MemoryError> {
+        for (i, &(target, _)) in self.feeds.iter().enumerate() {
+            if (target != libc::STDIN_FILENO && target < 3)
+                || self.feeds[..i].iter().any(|&(other, _)| other == target)
+            {
+                return Err(error(
+                    ErrorKind::InvalidInput,
+                    "invalid or duplicate target descriptor",
+                ));
+            }
+        }
+        // Pipe ends are kept above every target, so moving one pipe into
+        // place in the child never overwrites another.
+        let min_fd = self
+            .feeds
+            .iter()
+            .map(|&(target, _)| target)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let mut writers = Vec::with_capacity(self.feeds.len());
+        let mut readers = Vec::new();
+        for &(target, _) in &self.feeds {
+            let (read, write) = pipe(min_fd)?;
+            writers.push(write);
+            if target == libc::STDIN_FILENO {
+                self.command.stdin(Stdio::from(read));
+                continue;
+            }
+            // Stays in the command after the spawn; cleared below so a
+            // later spawn of the same command maps nothing.
+            let source = Arc::new(AtomicI32::new(read.as_raw_fd()));
+            let hook = Arc::clone(&source);
+            unsafe {
+                self.command.pre_exec(move || {
+                    let fd = hook.load(Ordering::SeqCst);
+                    if fd >= 0 && libc::dup2(fd, target) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            readers.push((read, source));
+        }
+
+        let spawned = self.command.spawn();
+        if self
+            .feeds
+            .iter()
+            .any(|&(target, _)| target == libc::STDIN_FILENO)
+        {
+            self.command.stdin(Stdio::null());
+        }
+        for (read, source) in readers {
+            source.store(-1, Ordering::SeqCst);
+            drop(read);
+        }
+        let mut child = spawned?;
+
+        for ((_, secret), write) in self.feeds.into_iter().zip(writers) {
+            if let Err(err) = secret.write_to_pipe(write.as_raw_fd()) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        }
+        Ok(child)
+    }
+}
+
+/// A secret type that [`SecretCommandExt`] can feed to a child:
+/// [`Secret`], [`SecretBytes`] or [`SecretString`].
+pub trait SecretSource: sealed::Sealed {
+    #[doc(hidden)]
+    fn write_to_pipe(&mut self, fd: RawFd) -> Result<(), MemoryError>;
+
+    #[doc(hidden)]
+    fn secret_len(&self) -> usize;
+}
+
+impl<const N: usize> SecretSource for Secret<N> {
+    fn write_to_pipe(&mut self, fd: RawFd) -> Result<(), MemoryError> {
+        self.write_to_fd(fd)
+    }
+
+    fn secret_len(&self) -> usize {
+        N
+    }
+}
+
+impl SecretSource for SecretBytes {
+    fn write_to_pipe(&mut self, fd: RawFd) -> Result<(), MemoryError> {
+        self.write_to_fd(fd)
+    }
+
+    fn secret_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl SecretSource for SecretString {
+    fn write_to_pipe(&mut self, fd: RawFd) -> Result<(), MemoryError> {
+        self.write_to_fd(fd)
+    }
+
+    fn secret_len(&self) -> usize {
+        self.len()
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl<const N: usize> Sealed for crate::Secret<N> {}
+    impl Sealed for crate::SecretBytes {}
+    impl Sealed for crate::SecretString {}
+}
+
+/// A close-on-exec pipe whose ends are numbered `min_fd` or higher.
+fn pipe(min_fd: RawFd) -> Result<(OwnedFd, OwnedFd), MemoryError> {
+    let mut fds = [0; 2];
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    let ret = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) };
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    let ret = unsafe { libc::pipe(fds.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let [read, write] = fds.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) });
+    Ok((move_above(read, min_fd)?, move_above(write, min_fd)?))
+}
+
+/// `fd` itself if it is numbered `min_fd` or higher, otherwise a
+/// close-on-exec duplicate that is. Also sets close-on-exec where `pipe2`
+/// is unavailable.
+fn move_above(fd: OwnedFd, min_fd: RawFd) -> Result<OwnedFd, MemoryError> {
+    if fd.as_raw_fd() >= min_fd
+        && cfg!(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd"
+        ))
+    {
+        return Ok(fd);
+    }
+    let dup = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, min_fd) };
+    if dup < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(dup) })
+}
+
+fn timed_out() -> MemoryError {
+    error(ErrorKind::TimedOut, "secret command timed out")
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
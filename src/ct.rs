@@ -0,0 +1,78 @@
+//! Constant-time byte primitives.
+//!
+//! Every function here touches every byte of its inputs, with no branch or
+//! table index that depends on their contents; the running time depends
+//! only on the lengths. Intermediate values pass through
+//! [`std::hint::black_box`] so the optimizer cannot prove them redundant and
+//! reintroduce an early exit. That hint is best effort, not a guarantee,
+//! which is also the position of the `subtle` crate.
+
+use std::hint::black_box;
+
+/// `0xFF` if `choice` is true, `0x00` otherwise, without a branch.
+pub(crate) fn mask(choice: bool) -> u8 {
+    black_box(0u8.wrapping_sub(choice as u8))
+}
+
+/// True iff `a` and `b` hold the same bytes. Lengths are public: slices of
+/// different lengths compare unequal without looking at the contents.
+pub(crate) fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut acc = 0u8;
+    for (x, y) in a.iter().zip(b) {
+        acc = black_box(acc | (x ^ y));
+    }
+    acc == 0
+}
+
+/// True iff `other`, zero-padded to `padded.len()`, equals `padded`.
+///
+/// The loop always runs over all of `padded`, so the time taken reveals
+/// `padded.len()` and `other.len()` but not where the meaningful bytes of
+/// `padded` end.
+pub(crate) fn eq_padded(padded: &[u8], other: &[u8]) -> bool {
+    if other.len() > padded.len() {
+        return false;
+    }
+    let mut acc = 0u8;
+    for (i, x) in padded.iter().enumerate() {
+        let y = other.get(i).copied().unwrap_or(0);
+        acc = black_box(acc | (x ^ y));
+    }
+    acc == 0
+}
+
+/// `dst = if choice { src } else { dst }`, byte by byte.
+pub(crate) fn assign(dst: &mut [u8], src: &[u8], choice: bool) {
+    let m = mask(choice);
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= m & (*d ^ s);
+    }
+}
+
+/// Swap `a` and `b` if `choice`, byte by byte.
+pub(crate) fn swap(a: &mut [u8], b: &mut [u8], choice: bool) {
+    let m = mask(choice);
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        let t = m & (*x ^ *y);
+        *x ^= t;
+        *y ^= t;
+    }
+}
+
+/// `0xFF` if `a < b`, `0x00` otherwise, without a branch.
+pub(crate) fn lt(a: u8, b: u8) -> u8 {
+    black_box(((a as u16).wrapping_sub(b as u16) >> 8) as u8)
+}
+
+/// `0xFF` if `a == b`, `0x00` otherwise, without a branch.
+pub(crate) fn eq_byte(a: u8, b: u8) -> u8 {
+    black_box((((a ^ b) as u16).wrapping_sub(1) >> 8) as u8)
+}
+
+/// `0xFF` if `lo <= c <= hi`, `0x00` otherwise, without a branch.
+pub(crate) fn in_range(c: u8, lo: u8, hi: u8) -> u8 {
+    !lt(c, lo) & !lt(hi, c)
+}
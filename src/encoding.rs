@@ -0,0 +1,324 @@
+//! Constant-time hex and base64 between encoded text and secrets.
+//!
+//! Decoders take the encoded text as a byte slice — a `&str` from a config
+//! file, or a guard of a secret that holds it — and write the decoded
+//! bytes straight into a new [`Secret`] or [`SecretBytes`]. Encoders write
+//! into a new [`SecretString`]. No decoded or encoded copy is left on the
+//! heap.
+//!
+//! ```
+//! use memsafe::encoding::{base64, hex};
+//!
+//! let mut key = hex::decode::<4>(b"deadBEEF").unwrap();
+//! assert_eq!(*key.read().unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+//!
+//! let mut header = base64::encode(base64::Alphabet::Standard, &*key.read().unwrap()).unwrap();
+//! assert_eq!(&*header.read().unwrap(), "3q2+7w==");
+//! ```
+//!
+//! Every character is mapped with arithmetic on masks rather than table
+//! lookups or branches, so the time taken depends only on the input
+//! length. Lengths are public, and so is whether the input was valid:
+//! invalid characters are collected over the whole input and reported
+//! once at the end, without saying where.
+
+use crate::ct::{eq_byte, in_range, lt};
+use crate::{MemoryError, Secret, SecretBytes, SecretString};
+
+/// Hexadecimal, two characters per byte.
+///
+/// Decoding accepts upper and lower case; encoding produces lower case.
+pub mod hex {
+    use super::*;
+
+    /// Decode exactly `2 * N` hex characters into a new `Secret<N>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidInput` if `input` is not
+    /// `2 * N` characters long, or of kind `InvalidData` if it holds a
+    /// non-hex character. The page is wiped first.
+    pub fn decode<const N: usize>(input: &[u8]) -> Result<Secret<N>, MemoryError> {
+        if input.len() != 2 * N {
+            return Err(invalid_input("hex input must be twice the secret size"));
+        }
+        Secret::try_new_with(|out| decode_into(input, out))
+    }
+
+    /// Decode hex characters into a new [`SecretBytes`] of half their
+    /// length.
+    ///
+    /// # Errors
+    ///
+    /// As for [`decode`]; the length must be even and non-zero.
+    pub fn decode_bytes(input: &[u8]) -> Result<SecretBytes, MemoryError> {
+        if input.len() % 2 != 0 {
+            return Err(invalid_input("hex input must have an even length"));
+        }
+        SecretBytes::try_new_with(input.len() / 2, |out| decode_into(input, out))
+    }
+
+    /// Encode `bytes` as lowercase hex into a new [`SecretString`].
+    pub fn encode(bytes: &[u8]) -> Result<SecretString, MemoryError> {
+        SecretString::new_with(2 * bytes.len(), |out| {
+            for (pair, &b) in out.chunks_exact_mut(2).zip(bytes) {
+                pair[0] = encode_nibble(b >> 4);
+                pair[1] = encode_nibble(b & 0x0f);
+            }
+        })
+    }
+
+    fn decode_into(input: &[u8], out: &mut [u8]) -> Result<(), MemoryError> {
+        let mut valid = 0xff;
+        for (b, pair) in out.iter_mut().zip(input.chunks_exact(2)) {
+            let (hi, hi_valid) = decode_char(pair[0]);
+            let (lo, lo_valid) = decode_char(pair[1]);
+            *b = (hi << 4) | lo;
+            valid &= hi_valid & lo_valid;
+        }
+        check(valid, "invalid hex character")
+    }
+
+    /// The value of hex digit `c`, and `0xFF` if `c` is one.
+    fn decode_char(c: u8) -> (u8, u8) {
+        let digit = in_range(c, b'0', b'9');
+        let lower = in_range(c, b'a', b'f');
+        let upper = in_range(c, b'A', b'F');
+        let value = (digit & c.wrapping_sub(b'0'))
+            | (lower & c.wrapping_sub(b'a' - 10))
+            | (upper & c.wrapping_sub(b'A' - 10));
+        (value, digit | lower | upper)
+    }
+
+    fn encode_nibble(n: u8) -> u8 {
+        // '0' + n, moved up to 'a' for n >= 10.
+        (b'0' + n).wrapping_add(!lt(n, 10) & (b'a' - b'0' - 10))
+    }
+}
+
+/// Base64 (RFC 4648), three bytes per four characters.
+pub mod base64 {
+    use super::*;
+
+    /// The two RFC 4648 base64 alphabets.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Alphabet {
+        /// `+` and `/`, padded with `=` when encoding.
+        Standard,
+        /// `-` and `_`, unpadded when encoding.
+        UrlSafe,
+    }
+
+    impl Alphabet {
+        /// The characters for values 62 and 63.
+        fn specials(self) -> (u8, u8) {
+            match self {
+                Alphabet::Standard => (b'+', b'/'),
+                Alphabet::UrlSafe => (b'-', b'_'),
+            }
+        }
+    }
+
+    /// Decode base64 into a new `Secret<N>`; the input must decode to
+    /// exactly `N` bytes. Padding is optional, but must be correct if
+    /// present.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidInput` if the input has an
+    /// impossible length or does not decode to `N` bytes, or of kind
+    /// `InvalidData` if it holds a character outside `alphabet` or
+    /// non-zero trailing bits. The page is wiped first.
+    pub fn decode<const N: usize>(
+        alphabet: Alphabet,
+        input: &[u8],
+    ) -> Result<Secret<N>, MemoryError> {
+        let input = strip_padding(input)?;
+        if decoded_len(input.len())? != N {
+            return Err(invalid_input("base64 input does not match the secret size"));
+        }
+        Secret::try_new_with(|out| decode_into(alphabet, input, out))
+    }
+
+    /// Decode base64 into a new [`SecretBytes`] of the decoded length.
+    ///
+    /// # Errors
+    ///
+    /// As for [`decode`]; empty input is rejected.
+    pub fn decode_bytes(alphabet: Alphabet, input: &[u8]) -> Result<SecretBytes, MemoryError> {
+        let input = strip_padding(input)?;
+        let len = decoded_len(input.len())?;
+        SecretBytes::try_new_with(len, |out| decode_into(alphabet, input, out))
+    }
+
+    /// Encode `bytes` into a new [`SecretString`], padded for
+    /// [`Alphabet::Standard`] and unpadded for [`Alphabet::UrlSafe`].
+    pub fn encode(alphabet: Alphabet, bytes: &[u8]) -> Result<SecretString, MemoryError> {
+        let unpadded = (bytes.len() * 4).div_ceil(3);
+        let len = match alphabet {
+            Alphabet::Standard => bytes.len().div_ceil(3) * 4,
+            Alphabet::UrlSafe => unpadded,
+        };
+        SecretString::new_with(len, |out| {
+            for (chars, group) in out.chunks_mut(4).zip(bytes.chunks(3)) {
+                let b = [
+                    group[0],
+                    group.get(1).copied().unwrap_or(0),
+                    group.get(2).copied().unwrap_or(0),
+                ];
+                let values = [
+                    b[0] >> 2,
+                    ((b[0] & 0x03) << 4) | (b[1] >> 4),
+                    ((b[1] & 0x0f) << 2) | (b[2] >> 6),
+                    b[2] & 0x3f,
+                ];
+                for (c, v) in chars.iter_mut().zip(values) {
+                    *c = encode_sextet(alphabet, v);
+                }
+            }
+            out[unpadded..].fill(b'=');
+        })
+    }
+
+    /// `input` without its trailing `=`, which is only allowed to bring the
+    /// length to a multiple of four. The padding length is public: it
+    /// follows from the decoded length.
+    fn strip_padding(input: &[u8]) -> Result<&[u8], MemoryError> {
+        let data = match input {
+            [rest @ .., b'=', b'='] => rest,
+            [rest @ .., b'='] => rest,
+            _ => return Ok(input),
+        };
+        if input.len() % 4 != 0 || data.len() % 4 == 1 {
+            return Err(invalid_input("invalid base64 padding"));
+        }
+        Ok(data)
+    }
+
+    fn decoded_len(chars: usize) -> Result<usize, MemoryError> {
+        if chars % 4 == 1 {
+            return Err(invalid_input("invalid base64 length"));
+        }
+        Ok(chars / 4 * 3 + (chars % 4).saturating_sub(1))
+    }
+
+    fn decode_into(alphabet: Alphabet, input: &[u8], out: &mut [u8]) -> Result<(), MemoryError> {
+        let mut valid = 0xff;
+        for (group, bytes) in input.chunks(4).zip(out.chunks_mut(3)) {
+            let mut acc = 0u32;
+            for (i, &c) in group.iter().enumerate() {
+                let (v, ok) = decode_char(alphabet, c);
+                acc |= (v as u32) << (18 - 6 * i);
+                valid &= ok;
+            }
+            let decoded = acc.to_be_bytes();
+            bytes.copy_from_slice(&decoded[1..1 + bytes.len()]);
+            // A short final group must not carry bits past the last byte.
+            let spare = (acc << (8 * bytes.len())) & 0x00ff_ffff;
+            valid &= eq_byte((spare | (spare >> 8) | (spare >> 16)) as u8, 0);
+        }
+        check(valid, "invalid base64 character")
+    }
+
+    /// The value of base64 character `c`, and `0xFF` if `c` is one.
+    fn decode_char(alphabet: Alphabet, c: u8) -> (u8, u8) {
+        let (c62, c63) = alphabet.specials();
+        let upper = in_range(c, b'A', b'Z');
+        let lower = in_range(c, b'a', b'z');
+        let digit = in_range(c, b'0', b'9');
+        let is62 = eq_byte(c, c62);
+        let is63 = eq_byte(c, c63);
+        let value = (upper & c.wrapping_sub(b'A'))
+            | (lower & c.wrapping_sub(b'a' - 26))
+            | (digit & c.wrapping_add(52 - b'0'))
+            | (is62 & 62)
+            | (is63 & 63);
+        (value, upper | lower | digit | is62 | is63)
+    }
+
+    fn encode_sextet(alphabet: Alphabet, v: u8) -> u8 {
+        let (c62, c63) = alphabet.specials();
+        // Start in 'A'..'Z' and shift into each later range in turn.
+        let mut c = v.wrapping_add(b'A');
+        c = c.wrapping_add(!lt(v, 26) & (b'a' - 26 - b'A'));
+        c = c.wrapping_add(!lt(v, 52) & b'0'.wrapping_sub(52).wrapping_sub(b'a' - 26));
+        c = c.wrapping_add(!lt(v, 62) & c62.wrapping_sub(b'0' + 10));
+        c = c.wrapping_add(!lt(v, 63) & c63.wrapping_sub(c62 + 1));
+        c
+    }
+}
+
+/// Turn an accumulated validity mask into the result.
+fn check(valid: u8, msg: &'static str) -> Result<(), MemoryError> {
+    if valid != 0xff {
+        return Err(MemoryError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            msg,
+        )));
+    }
+    Ok(())
+}
+
+fn invalid_input(msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+}
+
+/// The character mappings are private, so they are checked exhaustively
+/// here against the alphabets.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &[u8] = b"0123456789abcdef";
+    const STANDARD: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const URL_SAFE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    #[test]
+    fn hex_mapping_matches_the_alphabet() {
+        let mut secret = hex::encode(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]).unwrap();
+        assert_eq!(secret.read().unwrap().as_bytes(), HEX);
+        for c in 0..=255u8 {
+            let expected = HEX
+                .iter()
+                .position(|&h| h == c.to_ascii_lowercase())
+                .filter(|_| c.is_ascii_hexdigit());
+            let result = hex::decode::<1>(&[b'0', c]).ok();
+            assert_eq!(
+                result.map(|mut s| s.read().unwrap()[0] as usize),
+                expected,
+                "c = {c:#04x}"
+            );
+        }
+    }
+
+    #[test]
+    fn base64_mapping_matches_the_alphabets() {
+        for (alphabet, chars) in [
+            (base64::Alphabet::Standard, STANDARD),
+            (base64::Alphabet::UrlSafe, URL_SAFE),
+        ] {
+            for c in 0..=255u8 {
+                let expected = chars.iter().position(|&x| x == c);
+                // "A" + c + "AA": c lands in the high bits of the second
+                // byte, and the first byte picks up c's top two bits.
+                let result = base64::decode::<3>(alphabet, &[b'A', c, b'A', b'A']).ok();
+                assert_eq!(
+                    result.map(|mut s| {
+                        let b = *s.read().unwrap();
+                        ((b[0] as usize & 0x03) << 4) | (b[1] as usize >> 4)
+                    }),
+                    expected,
+                    "c = {c:#04x}"
+                );
+            }
+            // All 64 values in order encode to the alphabet itself.
+            let mut bytes = Vec::new();
+            for v in (0..64u32).collect::<Vec<_>>().chunks(4) {
+                let acc = (v[0] << 18) | (v[1] << 12) | (v[2] << 6) | v[3];
+                bytes.extend_from_slice(&acc.to_be_bytes()[1..]);
+            }
+            let mut encoded = base64::encode(alphabet, &bytes).unwrap();
+            assert_eq!(encoded.read().unwrap().as_bytes(), chars);
+        }
+    }
+}
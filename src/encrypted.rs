@@ -0,0 +1,215 @@
+//! Secrets that are encrypted while sealed.
+//!
+//! A plain [`Secret`](crate::Secret) relies on `PROT_NONE` to keep its page
+//! unreadable between uses. That stops stray pointers in this process, but
+//! not an attacker who reads memory around the page protections:
+//! `/proc/self/mem`, `process_vm_readv` from a same-uid process, or, on
+//! Windows, a read through the `PAGE_READONLY` floor.
+//!
+//! An [`EncryptedSecret`] additionally keeps only ciphertext in its page
+//! while sealed. The key is a random per-process key held in its own
+//! protected page. `read()` / `write()` decrypt in place, and the guard
+//! re-encrypts when it drops, so the plaintext exists only while a guard is
+//! alive.
+//!
+//! This raises the bar rather than closing the gap: the key page is
+//! subject to the same kind of reads. An attacker who can locate and read
+//! it as well can still decrypt. What encryption at rest removes is the
+//! single-page leak, such as one targeted read of the secret's address or
+//! one page landing in a report.
+//!
+//! ```
+//! use memsafe::encrypted::EncryptedSecret;
+//!
+//! let mut secret = EncryptedSecret::<32>::new_with(|buf| {
+//!     buf[..10].copy_from_slice(b"my-api-key");
+//! }).unwrap();
+//!
+//! let view = secret.read().unwrap();
+//! assert_eq!(&view[..10], b"my-api-key");
+//! ```
+//!
+//! The cipher is a type parameter implementing [`SealCipher`]. The default,
+//! [`ChaCha20`], is implemented in this crate and needs no dependency.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::cell::Cell;
+use crate::chacha::ChaCha20State;
+use crate::process_key::ProcessKey;
+use crate::{MemSafe, MemoryError};
+
+/// Length of the per-process sealing key, in bytes.
+pub const KEY_LEN: usize = crate::process_key::KEY_LEN;
+
+/// Length of the per-seal nonce, in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// A length-preserving cipher used to seal [`EncryptedSecret`] pages.
+///
+/// `apply` must be its own inverse for a given key and nonce, as a stream
+/// cipher's keystream XOR is: the same call both encrypts and decrypts.
+/// The crate never reuses a nonce for different plaintexts under one key.
+pub trait SealCipher {
+    /// Encrypt or decrypt `data` in place.
+    fn apply(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], data: &mut [u8]);
+}
+
+/// The default [`SealCipher`]: ChaCha20 as specified in RFC 8439.
+///
+/// The expanded cipher state is volatile-wiped when `apply` returns.
+pub struct ChaCha20;
+
+impl SealCipher for ChaCha20 {
+    fn apply(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+        let mut state = ChaCha20State::zeroed();
+        state.init(key, nonce, 0);
+        state.apply_keystream(data);
+    }
+}
+
+/// The per-process sealing key. Created on first use.
+static PROCESS_KEY: ProcessKey = ProcessKey::new();
+
+/// Monotonic part of every nonce handed out by this process.
+static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A nonce unique within this process (counter) and among live processes
+/// (pid), so a non-Linux child that inherited the key still never repeats
+/// one of its parent's nonces.
+fn fresh_nonce() -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    let counter = NONCE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    nonce[..8].copy_from_slice(&counter.to_le_bytes());
+    nonce[8..].copy_from_slice(&std::process::id().to_le_bytes());
+    nonce
+}
+
+/// A fixed-size secret that holds only ciphertext while sealed.
+///
+/// Access works like [`Secret`](crate::Secret): `read()` and `write()`
+/// return guards that deref to the plaintext. On top of the usual
+/// `mprotect` transitions, opening a guard decrypts the page in place and
+/// dropping it re-encrypts. A write guard re-encrypts under a fresh nonce.
+///
+/// Like `Secret`, it implements neither `Debug` nor `Display`.
+pub struct EncryptedSecret<const N: usize, C: SealCipher = ChaCha20> {
+    inner: MemSafe<[u8; N]>,
+    nonce: [u8; NONCE_LEN],
+    _cipher: PhantomData<fn() -> C>,
+}
+
+impl<const N: usize, C: SealCipher> EncryptedSecret<N, C> {
+    /// Allocate an `N`-byte encrypted secret and fill it in place.
+    ///
+    /// `init` writes the plaintext straight into the protected page, which
+    /// is encrypted before it is first sealed. If `init` panics or sealing
+    /// fails, the page is wiped and released as with
+    /// [`Secret::new_with`](crate::Secret::new_with).
+    pub fn new_with<F>(init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]),
+    {
+        let nonce = fresh_nonce();
+        let cell = Cell::<[u8; N]>::try_new_with(|page| {
+            init(page);
+            PROCESS_KEY.with(|key| C::apply(key, &nonce, page))
+        })?;
+        Ok(EncryptedSecret {
+            inner: MemSafe { cell },
+            nonce,
+            _cipher: PhantomData,
+        })
+    }
+
+    /// Decrypt the page and obtain temporary read access to the plaintext.
+    /// The page is re-encrypted and sealed when the guard drops.
+    pub fn read(&mut self) -> Result<EncryptedRead<'_, N, C>, MemoryError> {
+        self.unseal()?;
+        // From here on the guard owns re-encryption, including on the error
+        // path below.
+        let guard = EncryptedRead { secret: self };
+        guard.secret.inner.cell.read_only()?;
+        Ok(guard)
+    }
+
+    /// Decrypt the page and obtain temporary read-write access to the
+    /// plaintext. The page is re-encrypted under a fresh nonce and sealed
+    /// when the guard drops.
+    pub fn write(&mut self) -> Result<EncryptedWrite<'_, N, C>, MemoryError> {
+        self.unseal()?;
+        Ok(EncryptedWrite { secret: self })
+    }
+
+    /// Leaves the page read-write and decrypted. On error the page is
+    /// sealed again and still holds ciphertext.
+    fn unseal(&mut self) -> Result<(), MemoryError> {
+        self.inner.cell.read_write()?;
+        let page = &mut *self.inner.cell;
+        let nonce = &self.nonce;
+        if let Err(e) = PROCESS_KEY.with(|key| C::apply(key, nonce, page)) {
+            self.inner.cell.low_priv()?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Encrypt the (read-write, decrypted) page and lower its privilege.
+    fn reseal(&mut self) -> Result<(), MemoryError> {
+        self.inner.cell.read_write()?;
+        let page = &mut *self.inner.cell;
+        let nonce = &self.nonce;
+        PROCESS_KEY.with(|key| C::apply(key, nonce, page))?;
+        self.inner.cell.low_priv()
+    }
+}
+
+/// Read guard of an [`EncryptedSecret`]. Re-encrypts the page on drop.
+pub struct EncryptedRead<'a, const N: usize, C: SealCipher = ChaCha20> {
+    secret: &'a mut EncryptedSecret<N, C>,
+}
+
+impl<const N: usize, C: SealCipher> Deref for EncryptedRead<'_, N, C> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        self.secret.inner.cell.deref()
+    }
+}
+
+impl<const N: usize, C: SealCipher> Drop for EncryptedRead<'_, N, C> {
+    fn drop(&mut self) {
+        // The plaintext did not change, so sealing under the same nonce
+        // reproduces the previous ciphertext exactly.
+        self.secret.reseal().unwrap();
+    }
+}
+
+/// Write guard of an [`EncryptedSecret`]. Re-encrypts the page under a
+/// fresh nonce on drop.
+pub struct EncryptedWrite<'a, const N: usize, C: SealCipher = ChaCha20> {
+    secret: &'a mut EncryptedSecret<N, C>,
+}
+
+impl<const N: usize, C: SealCipher> Deref for EncryptedWrite<'_, N, C> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        self.secret.inner.cell.deref()
+    }
+}
+
+impl<const N: usize, C: SealCipher> DerefMut for EncryptedWrite<'_, N, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.secret.inner.cell.deref_mut()
+    }
+}
+
+impl<const N: usize, C: SealCipher> Drop for EncryptedWrite<'_, N, C> {
+    fn drop(&mut self) {
+        self.secret.nonce = fresh_nonce();
+        self.secret.reseal().unwrap();
+    }
+}
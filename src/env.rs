@@ -0,0 +1,157 @@
+//! Taking secrets out of the process environment.
+//!
+//! `std::env::var` copies a value onto the heap and leaves the original in
+//! the environment block, where `/proc/<pid>/environ` shows it and every
+//! child process inherits it. [`Secret::from_env`](crate::Secret::from_env)
+//! instead copies the value straight from the `environ` entry into a locked
+//! page, overwrites the entry's value bytes in place, and unsets the
+//! variable. The returned [`Scrub`] says which of those steps took effect.
+
+use std::ffi::c_char;
+
+use crate::MemoryError;
+use crate::ptr_ops::secure_zero;
+
+/// What [`Secret::from_env`](crate::Secret::from_env) removed from the
+/// environment after copying the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scrub {
+    /// No copy of the value is left in the environment memory.
+    ///
+    /// Every `environ` entry for the variable has its value bytes
+    /// overwritten with zeros. For a variable inherited at startup the
+    /// entry lives in the initial environment block, which is what
+    /// `/proc/<pid>/environ` shows. A value installed later with `setenv`
+    /// lives on the heap instead; if it replaced an inherited value, that
+    /// older copy is still in the initial block, beyond this crate's
+    /// reach, and this is `false`. On Linux the initial block is checked
+    /// through `/proc/self/environ` when it is readable.
+    pub overwritten: bool,
+    /// The variable is no longer in the environment, so child processes
+    /// will not inherit it.
+    pub unset: bool,
+}
+
+impl Scrub {
+    /// True if the value was both overwritten and unset.
+    pub fn is_complete(&self) -> bool {
+        self.overwritten && self.unset
+    }
+}
+
+/// Find `name` in the environment, hand its value to `copy`, then scrub
+/// it. The environment is left untouched if `copy` fails.
+///
+/// # Safety
+///
+/// No other thread may read or modify the environment during the call.
+pub(crate) unsafe fn take<T, F>(name: &str, copy: F) -> Result<(T, Scrub), MemoryError>
+where
+    F: FnOnce(&[u8]) -> Result<T, MemoryError>,
+{
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(invalid(
+            std::io::ErrorKind::InvalidInput,
+            "invalid variable name",
+        ));
+    }
+    // The first match is the one `getenv` would return.
+    let Some(value) = (unsafe { entries(name).next() }) else {
+        return Err(invalid(
+            std::io::ErrorKind::NotFound,
+            "environment variable not set",
+        ));
+    };
+    let result = copy(value)?;
+
+    for value in unsafe { entries(name) } {
+        // Entries are writable C strings owned by the process; only the
+        // value is cleared, so the entry stays a well-formed "NAME=".
+        secure_zero(value);
+    }
+    #[cfg(target_os = "linux")]
+    let overwritten = !initial_block_holds(name).unwrap_or(false);
+    #[cfg(not(target_os = "linux"))]
+    let overwritten = true;
+    unsafe { std::env::remove_var(name) };
+    let unset = unsafe { entries(name).next() }.is_none();
+    Ok((result, Scrub { overwritten, unset }))
+}
+
+/// Values of the `environ` entries named `name`, as mutable views into the
+/// entries themselves.
+unsafe fn entries<'a>(name: &'a str) -> impl Iterator<Item = &'a mut [u8]> + 'a {
+    let mut entry = unsafe { environ() };
+    std::iter::from_fn(move || {
+        if entry.is_null() {
+            return None;
+        }
+        loop {
+            let ptr = unsafe { *entry };
+            if ptr.is_null() {
+                return None;
+            }
+            entry = unsafe { entry.add(1) };
+            let len = unsafe { libc::strlen(ptr) };
+            let bytes = unsafe { std::slice::from_raw_parts_mut(ptr as *mut u8, len) };
+            if bytes.len() > name.len()
+                && bytes.starts_with(name.as_bytes())
+                && bytes[name.len()] == b'='
+            {
+                return Some(&mut bytes[name.len() + 1..]);
+            }
+        }
+    })
+}
+
+/// True if `/proc/self/environ` still shows a non-empty value for `name`.
+///
+/// The block holds other variables' secrets too, so it is read in chunks
+/// through a locked page and matched as a stream.
+#[cfg(target_os = "linux")]
+fn initial_block_holds(name: &str) -> Result<bool, MemoryError> {
+    use std::io::Read;
+
+    use crate::cell::Cell;
+
+    let mut file = std::fs::File::open("/proc/self/environ")?;
+    let mut chunk = Cell::<[u8]>::try_new_slice_with(4096, |_| Ok(()))?;
+    chunk.read_write()?;
+    let pattern = [name.as_bytes(), b"="].concat();
+    // Bytes of `pattern` matched so far in the current entry, or `None`
+    // once the entry is known to be another variable.
+    let mut matched = Some(0);
+    loop {
+        let n = match file.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for &byte in &chunk[..n] {
+            matched = match matched {
+                _ if byte == 0 => Some(0),
+                Some(i) if i == pattern.len() => return Ok(true),
+                Some(i) if pattern[i] == byte => Some(i + 1),
+                _ => None,
+            };
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+unsafe fn environ() -> *const *mut c_char {
+    unsafe { *libc::_NSGetEnviron() as *const *mut c_char }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+unsafe fn environ() -> *const *mut c_char {
+    unsafe extern "C" {
+        static environ: *const *mut c_char;
+    }
+    unsafe { environ }
+}
+
+fn invalid(kind: std::io::ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
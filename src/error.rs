@@ -1,24 +1,58 @@
 use std::error::Error;
 use std::fmt::Display;
 
+/// Errors returned by this crate.
+///
+/// Every variant wraps the underlying `io::Error`, available through
+/// [`MemoryError::inner`] and `Error::source`, so callers that only care
+/// about the OS-level cause can treat all variants alike.
 #[derive(Debug)]
-pub struct MemoryError(std::io::Error);
+#[non_exhaustive]
+pub enum MemoryError {
+    /// A memory-protection syscall failed, or the input was rejected before
+    /// any syscall was made (`ErrorKind::InvalidInput`).
+    Io(std::io::Error),
+    /// The operating system's random number generator could not supply
+    /// entropy.
+    Entropy(std::io::Error),
+    /// A secret no longer matches the MAC recorded when it was last
+    /// written: its bytes changed while sealed. See
+    /// [`integrity`](crate::integrity).
+    Integrity(std::io::Error),
+}
 
 impl From<std::io::Error> for MemoryError {
     fn from(err: std::io::Error) -> Self {
-        MemoryError(err)
+        MemoryError::Io(err)
     }
 }
 
 impl MemoryError {
     pub fn inner(&self) -> &std::io::Error {
-        &self.0
+        match self {
+            MemoryError::Io(err) | MemoryError::Entropy(err) | MemoryError::Integrity(err) => err,
+        }
+    }
+}
+
+impl MemoryError {
+    /// Re-tag an error raised while gathering randomness.
+    pub(crate) fn into_entropy(self) -> Self {
+        match self {
+            MemoryError::Io(err) | MemoryError::Entropy(err) | MemoryError::Integrity(err) => {
+                MemoryError::Entropy(err)
+            }
+        }
     }
 }
 
 impl Display for MemoryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Memory error: {}", self.0)
+        match self {
+            MemoryError::Io(err) => write!(f, "Memory error: {err}"),
+            MemoryError::Entropy(err) => write!(f, "Entropy source failed: {err}"),
+            MemoryError::Integrity(err) => write!(f, "Integrity check failed: {err}"),
+        }
     }
 }
 
@@ -27,7 +61,7 @@ impl Error for MemoryError {
     /// `eyre`, `thiserror::Error::source`-aware reporters, etc.) so they
     /// can drill into the root cause without parsing the `Display` text.
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.0)
+        Some(self.inner())
     }
 }
 
@@ -51,4 +85,23 @@ mod tests {
         ));
         assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
     }
+
+    #[test]
+    fn entropy_variant_is_distinct_and_exposes_source() {
+        let err = MemoryError::Entropy(std::io::Error::other("no entropy"));
+        assert!(matches!(err, MemoryError::Entropy(_)));
+        assert!(format!("{err}").starts_with("Entropy source failed:"));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn integrity_variant_is_distinct_and_exposes_source() {
+        let err = MemoryError::Integrity(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "mac mismatch",
+        ));
+        assert!(matches!(err, MemoryError::Integrity(_)));
+        assert!(format!("{err}").starts_with("Integrity check failed:"));
+        assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidData);
+    }
 }
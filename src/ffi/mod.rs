@@ -299,3 +299,47 @@ pub fn mem_no_dump<T>(ptr: *mut T, len: usize) -> Result<(), MemoryError> {
 pub fn mem_wipe_on_fork<T>(ptr: *mut T, len: usize) -> Result<(), MemoryError> {
     unix::madvice(ptr as *mut c_void, len, libc::MADV_WIPEONFORK)
 }
+
+/// Fills `buf` from the operating system's CSPRNG.
+///
+/// Short reads and `EINTR` are retried until the buffer is full, so a
+/// successful return means every byte came from the kernel. Failures are
+/// reported as [`MemoryError::Entropy`].
+///
+/// # Platform-specific Behavior
+///
+/// * **Linux / Android**: `getrandom(2)` with no flags, which blocks only
+///   until the kernel pool has been initialized once after boot.
+/// * **Other Unix**: reads `/dev/urandom`.
+/// * **Windows**: `RtlGenRandom`, in chunks that fit its `ULONG` length.
+pub fn fill_random(buf: &mut [u8]) -> Result<(), MemoryError> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match unix::getrandom(&mut buf[filled..], 0) {
+                Ok(n) => filled += n,
+                Err(e) if e.inner().kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into_entropy()),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    {
+        use std::io::Read;
+        // `read_exact` already retries on `Interrupted` and short reads.
+        std::fs::File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(buf))
+            .map_err(MemoryError::Entropy)
+    }
+
+    #[cfg(windows)]
+    {
+        for chunk in buf.chunks_mut(u32::MAX as usize) {
+            win::rtl_gen_random(chunk).map_err(MemoryError::into_entropy)?;
+        }
+        Ok(())
+    }
+}
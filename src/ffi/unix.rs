@@ -69,6 +69,19 @@ pub fn munmap<T>(ptr: *mut T, len: usize) -> Result<(), MemoryError> {
     }
 }
 
+/// Wrapper over `getrandom`. Full documentation with `man getrandom`.
+///
+/// Returns the number of bytes written, which may be short; callers loop.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn getrandom(buf: &mut [u8], flags: u32) -> Result<usize, MemoryError> {
+    let ret = unsafe { libc::getrandom(buf.as_mut_ptr() as *mut libc::c_void, buf.len(), flags) };
+    if ret < 0 {
+        Err(std::io::Error::last_os_error().into())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
 /// Error-branch tests: every wrapper must translate a failing syscall into
 /// `Err(MemoryError)` instead of silently returning `Ok`. Each test feeds the
 /// syscall an argument POSIX defines as invalid (unmapped address, overflowing
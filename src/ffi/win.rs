@@ -1,6 +1,7 @@
 use winapi::{
     ctypes::c_void,
     um::memoryapi::{VirtualAlloc, VirtualFree, VirtualLock, VirtualProtect, VirtualUnlock},
+    um::ntsecapi::RtlGenRandom,
 };
 
 use crate::error::MemoryError;
@@ -65,3 +66,13 @@ pub fn virtual_unlock<T>(ptr: *mut T, len: usize) -> Result<(), MemoryError> {
         Ok(())
     }
 }
+
+/// Wrapper over `RtlGenRandom` (`SystemFunction036`). Full documentation here:
+/// https://learn.microsoft.com/en-us/windows/win32/api/ntsecapi/nf-ntsecapi-rtlgenrandom
+pub fn rtl_gen_random(buf: &mut [u8]) -> Result<(), MemoryError> {
+    if unsafe { RtlGenRandom(buf.as_mut_ptr() as *mut c_void, buf.len() as u32) } == 0 {
+        Err(std::io::Error::last_os_error().into())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Loading secrets from key files and credential directories.
+//!
+//! [`SecretFile`] reads a file through its descriptor straight into a
+//! locked page, with no intermediate buffer, then asks the kernel to drop
+//! the file's pages from the page cache (`posix_fadvise(POSIX_FADV_DONTNEED)`
+//! where available). It can refuse files that other users may read, and
+//! shred and unlink one-shot credential files after reading them.
+//!
+//! ```no_run
+//! use memsafe::file::SecretFile;
+//!
+//! let mut key = SecretFile::new("/etc/myapp/master.key")
+//!     .require_private()
+//!     .read::<32>()
+//!     .unwrap();
+//! # let _ = key.read().unwrap();
+//! ```
+//!
+//! Services started with systemd's `LoadCredential=` or
+//! `SetCredentialEncrypted=` find their credentials with [`credential`]:
+//!
+//! ```no_run
+//! let mut token = memsafe::file::credential("api-token", 4096).unwrap();
+//! # let _ = token.read().unwrap();
+//! ```
+//!
+//! Dropping the page cache is advisory: tmpfs and ramfs, where credential
+//! directories and `/run/secrets` usually live, have no backing store, so
+//! their cached pages are the file itself. Shredding overwrites the file's
+//! blocks in place, which copy-on-write and journaling file systems and
+//! SSD firmware may not honour.
+
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::unix::fs::{FileExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::{MemoryError, Secret, SecretBytes};
+
+/// Reads a secret from a file. Built with [`SecretFile::new`], configured
+/// with its setters, and consumed by [`SecretFile::read`] or
+/// [`SecretFile::read_bytes`].
+pub struct SecretFile {
+    path: PathBuf,
+    private: bool,
+    shred: bool,
+}
+
+impl SecretFile {
+    /// Read `path`, accepting any permissions and leaving the file in
+    /// place.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        SecretFile {
+            path: path.as_ref().to_owned(),
+            private: false,
+            shred: false,
+        }
+    }
+
+    /// Refuse a file whose mode lets its group or other users read it. The
+    /// mode is checked on the opened descriptor, so the file cannot be
+    /// swapped between the check and the read.
+    pub fn require_private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    /// After a successful read, overwrite the file with zeros, flush it to
+    /// disk and unlink it. The file is opened for writing, so the caller
+    /// needs write access to it and its directory.
+    ///
+    /// A symlink is refused rather than followed, and the path is only
+    /// unlinked if it still names the file that was shredded.
+    pub fn shred_after_read(mut self) -> Self {
+        self.shred = true;
+        self
+    }
+
+    /// Read the whole file into a new secret of at most `N` bytes,
+    /// zero-padded.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Secret::from_reader`], plus the `io::Error` from opening,
+    /// shredding or unlinking the file wrapped in a `MemoryError`, and a
+    /// `MemoryError` of kind `PermissionDenied` if
+    /// [`SecretFile::require_private`] is set and the file is readable by
+    /// group or others. The file is not shredded if reading fails.
+    pub fn read<const N: usize>(&self) -> Result<Secret<N>, MemoryError> {
+        self.read_with(Secret::from_reader)
+    }
+
+    /// Read the whole file, at most `max` bytes, into a new
+    /// [`SecretBytes`] of the file's length.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SecretBytes::from_reader_limited`] and [`SecretFile::read`].
+    pub fn read_bytes(&self, max: usize) -> Result<SecretBytes, MemoryError> {
+        self.read_with(|file| SecretBytes::from_reader_limited(file, max))
+    }
+
+    fn read_with<T, F>(&self, read: F) -> Result<T, MemoryError>
+    where
+        F: FnOnce(&mut File) -> Result<T, MemoryError>,
+    {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(self.shred)
+            .custom_flags(if self.shred { libc::O_NOFOLLOW } else { 0 })
+            .open(&self.path)?;
+        let metadata = file.metadata()?;
+        if self.private && metadata.permissions().mode() & 0o044 != 0 {
+            return Err(error(
+                ErrorKind::PermissionDenied,
+                "secret file is readable by group or others",
+            ));
+        }
+        if self.shred && !metadata.is_file() {
+            return Err(error(
+                ErrorKind::InvalidInput,
+                "only regular files can be shredded",
+            ));
+        }
+
+        let result = read(&mut file);
+        if result.is_ok() && self.shred {
+            shred(&file, metadata.len())?;
+            let linked = std::fs::symlink_metadata(&self.path)?;
+            if (linked.dev(), linked.ino()) != (metadata.dev(), metadata.ino()) {
+                return Err(error(
+                    ErrorKind::Other,
+                    "secret file was replaced before it could be unlinked",
+                ));
+            }
+            std::fs::remove_file(&self.path)?;
+        }
+        drop_cache(&file);
+        result
+    }
+}
+
+/// Path of the systemd credential `name`, in `$CREDENTIALS_DIRECTORY`.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `NotFound` if the service has no
+/// credentials directory, or of kind `InvalidInput` if `name` is not a
+/// plain file name.
+pub fn credential_path(name: &str) -> Result<PathBuf, MemoryError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        return Err(error(ErrorKind::InvalidInput, "invalid credential name"));
+    }
+    let dir = std::env::var_os("CREDENTIALS_DIRECTORY")
+        .ok_or_else(|| error(ErrorKind::NotFound, "CREDENTIALS_DIRECTORY is not set"))?;
+    Ok(Path::new(&dir).join(name))
+}
+
+/// Read the systemd credential `name`, at most `max` bytes, into a new
+/// [`SecretBytes`]. The file must not be readable by group or others,
+/// which systemd guarantees.
+///
+/// # Errors
+///
+/// As for [`credential_path`] and [`SecretFile::read_bytes`].
+pub fn credential(name: &str, max: usize) -> Result<SecretBytes, MemoryError> {
+    SecretFile::new(credential_path(name)?)
+        .require_private()
+        .read_bytes(max)
+}
+
+/// Overwrite the first `len` bytes of `file` with zeros and flush them.
+fn shred(file: &File, len: u64) -> Result<(), MemoryError> {
+    let zeros = [0u8; 4096];
+    let mut offset = 0;
+    while offset < len {
+        let n = (len - offset).min(zeros.len() as u64) as usize;
+        file.write_all_at(&zeros[..n], offset)?;
+        offset += n as u64;
+    }
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Ask the kernel to evict the file's cached pages. Advisory; failures,
+/// such as `ESPIPE` on a pipe, are ignored.
+fn drop_cache(file: &File) {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    {
+        use std::os::fd::AsRawFd;
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    let _ = file;
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
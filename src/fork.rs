@@ -0,0 +1,80 @@
+//! Detecting that the current process is a fork of the one that created a
+//! value.
+//!
+//! A [`ForkDetector`] owns a tiny page holding a non-zero sentinel. On Linux
+//! the page is marked `MADV_WIPEONFORK`, like every page this crate
+//! allocates, so the kernel hands a forked child a zeroed copy and
+//! [`ForkDetector::has_forked`] is a single memory read. This also catches
+//! children created by a raw `fork`/`clone` syscall, which bypasses
+//! `pthread_atfork` handlers. On other platforms the detector compares the
+//! process id recorded at creation instead.
+//!
+//! ```
+//! use memsafe::fork::ForkDetector;
+//!
+//! let mut detector = ForkDetector::new().unwrap();
+//! assert!(!detector.has_forked());
+//!
+//! // In a child: drop pooled connections, cached keys, nonce state...
+//! if detector.has_forked() {
+//!     detector.rearm().unwrap();
+//! }
+//! ```
+
+use crate::MemoryError;
+use crate::cell::Cell;
+
+const SENTINEL: u64 = u64::MAX;
+
+/// Reports whether the process has forked since the detector was created or
+/// last re-armed.
+pub struct ForkDetector {
+    /// Read-only for the detector's whole life; the sentinel is not secret.
+    page: Cell<u64>,
+    #[cfg(not(target_os = "linux"))]
+    pid: u32,
+}
+
+// The page is owned exclusively by this value and only written through
+// `&mut self`.
+unsafe impl Send for ForkDetector {}
+unsafe impl Sync for ForkDetector {}
+
+impl ForkDetector {
+    /// Create a detector armed in the current process.
+    pub fn new() -> Result<Self, MemoryError> {
+        let mut page = Cell::new(SENTINEL)?;
+        page.read_only()?;
+        Ok(ForkDetector {
+            page,
+            #[cfg(not(target_os = "linux"))]
+            pid: std::process::id(),
+        })
+    }
+
+    /// True if this process is a fork of the one that created or last
+    /// re-armed the detector.
+    pub fn has_forked(&self) -> bool {
+        // Volatile, so the compiler cannot reuse a value read before a fork
+        // it does not know about.
+        let sentinel = unsafe { std::ptr::read_volatile(&*self.page) };
+        #[cfg(not(target_os = "linux"))]
+        if self.pid != std::process::id() {
+            return true;
+        }
+        sentinel != SENTINEL
+    }
+
+    /// Arm the detector in the current process, typically after a child has
+    /// handled the fork.
+    pub fn rearm(&mut self) -> Result<(), MemoryError> {
+        self.page.read_write()?;
+        *self.page = SENTINEL;
+        self.page.read_only()?;
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.pid = std::process::id();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Password and token generation into protected memory.
+//!
+//! Generated characters are written straight into the destination page,
+//! and the random bytes they are drawn from live only in a protected
+//! scratch page that is wiped when generation finishes. Nothing passes
+//! through a heap `String` on the way.
+//!
+//! Characters are picked by rejection sampling, so every character of the
+//! alphabet is equally likely regardless of its size (no modulo bias).
+//!
+//! ```
+//! use memsafe::generate::{self, CharClass, TokenFormat};
+//!
+//! // 20 characters, at least one of each class.
+//! let mut password = generate::password(20, &[
+//!     CharClass::required(generate::LOWERCASE),
+//!     CharClass::required(generate::UPPERCASE),
+//!     CharClass::required(generate::DIGITS),
+//!     CharClass::optional(generate::SYMBOLS),
+//! ]).unwrap();
+//! assert_eq!(password.read().unwrap().len(), 20);
+//!
+//! // A 256-bit API token, base64url-encoded (43 characters).
+//! let mut token = generate::token(TokenFormat::Base64Url, 43).unwrap();
+//! # let _ = token.read().unwrap();
+//! ```
+
+use crate::cell::Cell;
+use crate::ffi::fill_random;
+use crate::{MemoryError, SecretString};
+
+/// `a`–`z`.
+pub const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+/// `A`–`Z`.
+pub const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// `0`–`9`.
+pub const DIGITS: &[u8] = b"0123456789";
+/// The printable ASCII punctuation characters.
+pub const SYMBOLS: &[u8] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// A set of characters a password may draw from, and whether at least one
+/// of them must appear.
+///
+/// Characters must be ASCII, so that every generated password is valid
+/// UTF-8 with one byte per character.
+#[derive(Clone, Copy)]
+pub struct CharClass<'a> {
+    pub chars: &'a [u8],
+    pub required: bool,
+}
+
+impl<'a> CharClass<'a> {
+    /// A class of which at least one character must appear.
+    pub const fn required(chars: &'a [u8]) -> Self {
+        CharClass {
+            chars,
+            required: true,
+        }
+    }
+
+    /// A class whose characters may appear but are not required.
+    pub const fn optional(chars: &'a [u8]) -> Self {
+        CharClass {
+            chars,
+            required: false,
+        }
+    }
+}
+
+/// Encodings for random tokens.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TokenFormat {
+    /// Lowercase hexadecimal, 4 bits per character.
+    Hex,
+    /// RFC 4648 base32 alphabet without padding, 5 bits per character.
+    Base32,
+    /// RFC 4648 URL-safe base64 alphabet without padding, 6 bits per
+    /// character.
+    Base64Url,
+}
+
+impl TokenFormat {
+    /// The characters of this encoding, in digit order.
+    pub const fn alphabet(self) -> &'static [u8] {
+        match self {
+            TokenFormat::Hex => b"0123456789abcdef",
+            TokenFormat::Base32 => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567",
+            TokenFormat::Base64Url => {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_"
+            }
+        }
+    }
+
+    /// Entropy carried by each character, in bits.
+    pub const fn bits_per_char(self) -> usize {
+        match self {
+            TokenFormat::Hex => 4,
+            TokenFormat::Base32 => 5,
+            TokenFormat::Base64Url => 6,
+        }
+    }
+}
+
+/// Generate a `length`-character password drawn from the union of
+/// `classes`, with at least one character from every required class.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidInput` if the classes are empty
+/// or contain non-ASCII bytes, or if `length` is shorter than the number of
+/// required classes. Entropy and protection failures are reported as for
+/// [`Secret::random`](crate::Secret::random).
+pub fn password(length: usize, classes: &[CharClass<'_>]) -> Result<SecretString, MemoryError> {
+    let policy = Policy::new(classes)?;
+    policy.check_length(length)?;
+    SecretString::try_new_with(length, |page| policy.fill(page))
+}
+
+/// Generate a `length`-character random token in `format`.
+///
+/// The token carries `length * format.bits_per_char()` bits of entropy;
+/// for a 256-bit token use 64 hex, 52 base32 or 43 base64url characters.
+pub fn token(format: TokenFormat, length: usize) -> Result<SecretString, MemoryError> {
+    password(length, &[CharClass::optional(format.alphabet())])
+}
+
+/// Fill `out` in place with a password drawn from `classes`, under the same
+/// rules as [`password`]. Use this to generate into a guard of an existing
+/// secret:
+///
+/// ```
+/// use memsafe::Secret;
+/// use memsafe::generate::{self, CharClass};
+///
+/// let mut pin = Secret::<6>::new_with(|_| {}).unwrap();
+/// generate::fill(&mut pin.write().unwrap()[..], &[CharClass::required(generate::DIGITS)]).unwrap();
+/// assert!(pin.read().unwrap().iter().all(u8::is_ascii_digit));
+/// ```
+///
+/// On error `out` may hold a partial password; wipe or discard it.
+pub fn fill(out: &mut [u8], classes: &[CharClass<'_>]) -> Result<(), MemoryError> {
+    let policy = Policy::new(classes)?;
+    policy.check_length(out.len())?;
+    policy.fill(out)
+}
+
+/// Whole-password draws [`Policy::fill`] makes before it stops rejecting
+/// candidates and places the required classes directly.
+const MAX_DRAWS: usize = 1000;
+
+/// The validated, de-duplicated form of a `&[CharClass]`.
+struct Policy<'a> {
+    /// The alphabet in ascending byte order, `size` entries used.
+    alphabet: [u8; 128],
+    size: usize,
+    required: Vec<&'a [u8]>,
+}
+
+impl<'a> Policy<'a> {
+    fn new(classes: &[CharClass<'a>]) -> Result<Self, MemoryError> {
+        let mut member = [false; 128];
+        let mut required = Vec::new();
+        for class in classes {
+            if class.chars.is_empty() || !class.chars.is_ascii() {
+                return Err(invalid("character classes must be non-empty and ASCII"));
+            }
+            for &c in class.chars {
+                member[c as usize] = true;
+            }
+            if class.required {
+                required.push(class.chars);
+            }
+        }
+        // Duplicates across classes would make some characters more likely
+        // than others, so the alphabet is rebuilt from the membership table.
+        let mut alphabet = [0u8; 128];
+        let mut size = 0;
+        for (c, _) in member.iter().enumerate().filter(|(_, m)| **m) {
+            alphabet[size] = c as u8;
+            size += 1;
+        }
+        if size == 0 {
+            return Err(invalid("at least one character class is required"));
+        }
+        Ok(Policy {
+            alphabet,
+            size,
+            required,
+        })
+    }
+
+    fn check_length(&self, length: usize) -> Result<(), MemoryError> {
+        if length < self.required.len() {
+            return Err(invalid(
+                "length is shorter than the number of required classes",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draw whole passwords until one contains every required class.
+    /// Rejecting whole candidates keeps the result uniform over all valid
+    /// passwords, unlike patching a missing class into a fixed position.
+    ///
+    /// Policies where a random draw rarely qualifies, such as many small
+    /// required classes at a length close to their number, would take
+    /// unboundedly long that way. After [`MAX_DRAWS`] candidates, one
+    /// character is placed from each required class, the rest are drawn
+    /// from the whole alphabet, and the result is shuffled.
+    fn fill(&self, out: &mut [u8]) -> Result<(), MemoryError> {
+        let mut random = RandomBytes::new()?;
+        for _ in 0..MAX_DRAWS {
+            for c in out.iter_mut() {
+                *c = self.alphabet[random.below(self.size)?];
+            }
+            let complete = self
+                .required
+                .iter()
+                .all(|class| out.iter().any(|c| class.contains(c)));
+            if complete {
+                return Ok(());
+            }
+        }
+
+        let (seeded, rest) = out.split_at_mut(self.required.len());
+        for (c, class) in seeded.iter_mut().zip(&self.required) {
+            *c = class[random.below(class.len())?];
+        }
+        for c in rest.iter_mut() {
+            *c = self.alphabet[random.below(self.size)?];
+        }
+        // Fisher–Yates, so the seeded characters can land anywhere.
+        for i in (1..out.len()).rev() {
+            out.swap(i, random.below(i + 1)?);
+        }
+        Ok(())
+    }
+}
+
+/// Random bytes served from a protected scratch page, refilled from the OS
+/// as they are used up. The page is wiped when this is dropped.
+struct RandomBytes {
+    page: Cell<[u8; 256]>,
+    used: usize,
+}
+
+impl RandomBytes {
+    fn new() -> Result<Self, MemoryError> {
+        let mut page = Cell::<[u8; 256]>::new_with(|_| {})?;
+        page.read_write()?;
+        Ok(RandomBytes { page, used: 256 })
+    }
+
+    fn next(&mut self) -> Result<u8, MemoryError> {
+        if self.used == self.page.len() {
+            fill_random(&mut self.page[..])?;
+            self.used = 0;
+        }
+        let byte = self.page[self.used];
+        self.used += 1;
+        Ok(byte)
+    }
+
+    /// A uniform value in `0..n` for `n >= 1`, by rejection sampling:
+    /// draws at or above the largest multiple of `n` are discarded. Values
+    /// up to 256 use one byte per draw, larger ones eight.
+    fn below(&mut self, n: usize) -> Result<usize, MemoryError> {
+        if n <= 256 {
+            let limit = 256 - 256 % n;
+            loop {
+                let byte = self.next()? as usize;
+                if byte < limit {
+                    return Ok(byte % n);
+                }
+            }
+        }
+        let n = n as u64;
+        let limit = u64::MAX - u64::MAX % n;
+        loop {
+            let mut draw = 0u64;
+            for _ in 0..8 {
+                draw = draw << 8 | self.next()? as u64;
+            }
+            if draw < limit {
+                return Ok((draw % n) as usize);
+            }
+        }
+    }
+}
+
+fn invalid(msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+}
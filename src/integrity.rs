@@ -0,0 +1,260 @@
+//! Secrets whose contents are verified on every unseal.
+//!
+//! A [`CheckedSecret`] keeps an HMAC-SHA256 of its bytes in a second
+//! protected page, under a random per-process key in a third. The MAC is
+//! recomputed when a write guard drops, and checked before every `read()`
+//! and `write()`. If the bytes changed in between — a bit flip, a stray
+//! write through a leaked pointer, tampering through `/proc/self/mem` — the
+//! call fails with [`MemoryError::Integrity`] instead of handing out
+//! silently altered key material.
+//!
+//! ```
+//! use memsafe::integrity::CheckedSecret;
+//!
+//! let mut secret = CheckedSecret::<32>::new_with(|buf| {
+//!     buf[..10].copy_from_slice(b"my-api-key");
+//! }).unwrap();
+//!
+//! secret.write().unwrap()[10] = b'!'; // MAC updated when the guard drops
+//! assert_eq!(&secret.read().unwrap()[..11], b"my-api-key!");
+//! ```
+//!
+//! The MAC also covers the secret's address, so the contents and MAC of
+//! one secret cannot be copied over another. On Linux a forked child's
+//! pages, key page included, are wiped by the kernel; a `CheckedSecret`
+//! used in a child therefore fails its check rather than yielding zeros.
+//!
+//! A process-wide handler, set with [`set_failure_handler`], runs on every
+//! detected mismatch, before the error is returned: for example to log, or
+//! to abort the process.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, PoisonError};
+
+use crate::cell::Cell;
+use crate::ct;
+use crate::process_key::ProcessKey;
+use crate::ptr_ops::{ptr_fill_zero, secure_zero};
+use crate::sha256::{DIGEST_LEN, HmacSha256};
+use crate::{MemSafe, MemSafeRead, MemSafeWrite, MemoryError};
+
+/// The per-process MAC key. Created on first use.
+static MAC_KEY: ProcessKey = ProcessKey::new();
+
+static FAILURE_HANDLER: Mutex<Option<fn(&MemoryError)>> = Mutex::new(None);
+
+/// Install `handler` to run whenever a [`CheckedSecret`] fails its
+/// integrity check, replacing any previous handler. `None` removes it.
+///
+/// ```
+/// use memsafe::integrity;
+///
+/// integrity::set_failure_handler(Some(|err| {
+///     eprintln!("secret corrupted: {err}");
+///     std::process::abort();
+/// }));
+/// # integrity::set_failure_handler(None);
+/// ```
+pub fn set_failure_handler(handler: Option<fn(&MemoryError)>) {
+    *FAILURE_HANDLER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = handler;
+}
+
+/// A fixed-size secret that is checked against a keyed MAC on every unseal.
+///
+/// Like [`Secret`](crate::Secret), it implements neither `Debug` nor
+/// `Display`.
+pub struct CheckedSecret<const N: usize> {
+    inner: MemSafe<[u8; N]>,
+    mac: Cell<MacPage>,
+}
+
+// Both pages are owned exclusively by this value, like `MemSafe`'s.
+unsafe impl<const N: usize> Send for CheckedSecret<N> {}
+
+impl<const N: usize> CheckedSecret<N> {
+    /// Allocate an `N`-byte checked secret, fill it in place, and record its
+    /// MAC. See [`Secret::new_with`](crate::Secret::new_with) for the
+    /// construction guarantees.
+    pub fn new_with<F>(init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]),
+    {
+        let mut inner = MemSafe {
+            cell: Cell::<[u8; N]>::new_with(init)?,
+        };
+        let mut mac = mac_page()?;
+        mac.load_key()?;
+        mac.record(&inner.read()?[..]);
+        Ok(CheckedSecret { inner, mac })
+    }
+
+    /// Verify the secret and obtain temporary read access to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::Integrity`] if the bytes no longer match
+    /// their MAC, after running the failure handler.
+    pub fn read(&mut self) -> Result<MemSafeRead<'_, [u8; N]>, MemoryError> {
+        let view = self.inner.read()?;
+        verify_mac(&mut self.mac, &view[..])?;
+        Ok(view)
+    }
+
+    /// Verify the secret and obtain temporary read-write access to it. The
+    /// MAC is recomputed when the guard drops.
+    ///
+    /// # Errors
+    ///
+    /// As for [`CheckedSecret::read`].
+    pub fn write(&mut self) -> Result<CheckedWrite<'_, N>, MemoryError> {
+        verify_mac(&mut self.mac, &self.inner.read()?[..])?;
+        // Keyed now, so that recording the new MAC on drop cannot fail.
+        self.mac.load_key()?;
+        Ok(CheckedWrite {
+            guard: self.inner.write()?,
+            mac: &mut self.mac,
+        })
+    }
+
+    /// Check the secret against its MAC without handing out a guard.
+    pub fn verify(&mut self) -> Result<(), MemoryError> {
+        self.read().map(drop)
+    }
+}
+
+/// Write guard of a [`CheckedSecret`]. Records the MAC of the new contents
+/// on drop, then seals the page.
+pub struct CheckedWrite<'a, const N: usize> {
+    guard: MemSafeWrite<'a, [u8; N]>,
+    mac: &'a mut Cell<MacPage>,
+}
+
+impl<const N: usize> Deref for CheckedWrite<'_, N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<const N: usize> DerefMut for CheckedWrite<'_, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<const N: usize> Drop for CheckedWrite<'_, N> {
+    fn drop(&mut self) {
+        // Runs before `guard` drops, while the page is still readable. The
+        // HMAC was keyed by `write()`, so this allocates nothing and makes
+        // no system call.
+        self.mac.record(&self.guard[..]);
+    }
+}
+
+/// The recorded MAC of one secret, and the state to recompute it.
+///
+/// The page is allocated with the secret and stays read-write for its
+/// lifetime, so recording a MAC when a write guard drops cannot fail. The
+/// MAC is not secret: overwriting it only makes the next check fail. The
+/// keyed HMAC state is wiped whenever a computation finishes.
+struct MacPage {
+    mac: [u8; DIGEST_LEN],
+    hmac: HmacSha256,
+    tag: [u8; DIGEST_LEN],
+}
+
+fn mac_page() -> Result<Cell<MacPage>, MemoryError> {
+    let mut cell = Cell::new(MacPage {
+        mac: [0; DIGEST_LEN],
+        hmac: HmacSha256::zeroed(),
+        tag: [0; DIGEST_LEN],
+    })?;
+    cell.read_write()?;
+    Ok(cell)
+}
+
+impl MacPage {
+    /// Key the HMAC state with the process MAC key.
+    fn load_key(&mut self) -> Result<(), MemoryError> {
+        MAC_KEY.with(|key| self.hmac.init(key))
+    }
+
+    /// MAC `data`, bound to its address, into `tag`, then wipe the keyed
+    /// state. The state must have been keyed by [`MacPage::load_key`].
+    fn compute(&mut self, data: &[u8]) {
+        self.hmac
+            .update(&(data.as_ptr() as usize as u64).to_le_bytes());
+        self.hmac.update(data);
+        self.hmac.finish(&mut self.tag);
+        ptr_fill_zero(&mut self.hmac);
+    }
+
+    /// Compute and keep the MAC of `data`.
+    fn record(&mut self, data: &[u8]) {
+        self.compute(data);
+        self.mac = self.tag;
+        secure_zero(&mut self.tag);
+    }
+}
+
+/// Compare the MAC of `data` with the recorded one in constant time, and
+/// report a mismatch through the failure handler.
+fn verify_mac(mac: &mut Cell<MacPage>, data: &[u8]) -> Result<(), MemoryError> {
+    mac.load_key()?;
+    mac.compute(data);
+    let matches = ct::eq(&mac.mac, &mac.tag);
+    secure_zero(&mut mac.tag);
+    if matches {
+        return Ok(());
+    }
+    let err = MemoryError::Integrity(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "secret changed since it was last written",
+    ));
+    let handler = *FAILURE_HANDLER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(handler) = handler {
+        handler(&err);
+    }
+    Err(err)
+}
+
+/// Tampering is simulated by writing to the private page directly, which
+/// works on every platform; `tests/integrity.rs` does it through
+/// `/proc/self/mem` on Linux.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flipped_bit_is_detected_on_read_and_write() {
+        let mut secret = CheckedSecret::<16>::new_with(|b| b.fill(0x11)).unwrap();
+        secret.verify().unwrap();
+        secret.inner.cell.read_write().unwrap();
+        secret.inner.cell[5] ^= 0x01;
+        secret.inner.cell.low_priv().unwrap();
+
+        assert!(matches!(secret.read(), Err(MemoryError::Integrity(_))));
+        assert!(matches!(secret.write(), Err(MemoryError::Integrity(_))));
+    }
+
+    #[test]
+    fn swapped_contents_and_mac_are_detected() {
+        let mut a = CheckedSecret::<16>::new_with(|b| b.fill(0xaa)).unwrap();
+        let mut b = CheckedSecret::<16>::new_with(|b| b.fill(0xbb)).unwrap();
+        for s in [&mut a, &mut b] {
+            s.inner.cell.read_write().unwrap();
+        }
+        std::mem::swap(&mut *a.inner.cell, &mut *b.inner.cell);
+        std::mem::swap(&mut a.mac.mac, &mut b.mac.mac);
+        for s in [&mut a, &mut b] {
+            s.inner.cell.low_priv().unwrap();
+        }
+        assert!(matches!(a.verify(), Err(MemoryError::Integrity(_))));
+        assert!(matches!(b.verify(), Err(MemoryError::Integrity(_))));
+    }
+}
@@ -0,0 +1,187 @@
+//! Keyed hashing and key derivation inside protected memory.
+//!
+//! HMAC-SHA256 (RFC 2104), HKDF (RFC 5869) and PBKDF2-HMAC-SHA256
+//! (RFC 8018), implemented in this crate. Keys are taken as `&[u8]`, so
+//! they can be passed straight from a guard; every intermediate value —
+//! hash state, padded keys, PBKDF2's running blocks — lives in a protected
+//! scratch page that is wiped when the call returns; and results are
+//! written directly into the page of a new [`Secret`] or [`SecretBytes`].
+//!
+//! ```
+//! use memsafe::{Secret, kdf};
+//!
+//! let mut master = Secret::<32>::random().unwrap();
+//!
+//! // Extract once, then expand a key per tenant.
+//! let mut prk = kdf::hkdf_extract(b"app-salt", &master.read().unwrap()[..]).unwrap();
+//! let mut tenant_key: Secret<32> =
+//!     kdf::hkdf_expand(&prk.read().unwrap()[..], b"tenant:42").unwrap();
+//! # let _ = tenant_key.read().unwrap();
+//! ```
+
+use crate::cell::Cell;
+use crate::sha256::{DIGEST_LEN, HmacSha256};
+use crate::{MemoryError, Secret, SecretBytes};
+
+/// The longest output HKDF-Expand can produce: 255 blocks of 32 bytes.
+pub const HKDF_MAX_LEN: usize = 255 * DIGEST_LEN;
+
+/// Compute HMAC-SHA256 of `message` under `key`.
+///
+/// The MAC is returned as a secret since it is often used as a key itself.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<Secret<DIGEST_LEN>, MemoryError> {
+    let mut scratch = scratch()?;
+    Secret::try_new_with(|out| {
+        scratch.hmac.init(key);
+        scratch.hmac.update(message);
+        scratch.hmac.finish(out);
+        Ok(())
+    })
+}
+
+/// HKDF-Extract: condense input keying material `ikm` into a 32-byte
+/// pseudorandom key, using `salt` (which may be empty).
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Result<Secret<DIGEST_LEN>, MemoryError> {
+    hmac_sha256(salt, ikm)
+}
+
+/// HKDF-Expand: derive an `N`-byte key from the pseudorandom key `prk` and
+/// the context string `info`.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidInput` if `prk` is shorter than
+/// 32 bytes or `N` exceeds [`HKDF_MAX_LEN`].
+pub fn hkdf_expand<const N: usize>(prk: &[u8], info: &[u8]) -> Result<Secret<N>, MemoryError> {
+    check_expand(prk, N)?;
+    let mut scratch = scratch()?;
+    Secret::try_new_with(|out| {
+        scratch.expand(prk, info, out);
+        Ok(())
+    })
+}
+
+/// [`hkdf_expand`] with the output length chosen at runtime.
+pub fn hkdf_expand_bytes(prk: &[u8], info: &[u8], len: usize) -> Result<SecretBytes, MemoryError> {
+    check_expand(prk, len)?;
+    let mut scratch = scratch()?;
+    SecretBytes::try_new_with(len, |out| {
+        scratch.expand(prk, info, out);
+        Ok(())
+    })
+}
+
+/// PBKDF2-HMAC-SHA256: derive an `N`-byte key from `password` and `salt`
+/// with `iterations` rounds.
+///
+/// Choose `iterations` as high as your latency budget allows; OWASP
+/// currently recommends at least 600 000 for this construction.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidInput` if `iterations` is zero.
+pub fn pbkdf2_sha256<const N: usize>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<Secret<N>, MemoryError> {
+    check_iterations(iterations)?;
+    let mut scratch = scratch()?;
+    Secret::try_new_with(|out| {
+        scratch.pbkdf2(password, salt, iterations, out);
+        Ok(())
+    })
+}
+
+/// [`pbkdf2_sha256`] with the output length chosen at runtime.
+pub fn pbkdf2_sha256_bytes(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Result<SecretBytes, MemoryError> {
+    check_iterations(iterations)?;
+    let mut scratch = scratch()?;
+    SecretBytes::try_new_with(len, |out| {
+        scratch.pbkdf2(password, salt, iterations, out);
+        Ok(())
+    })
+}
+
+/// Everything a derivation computes from the key, kept together so it can
+/// live in one protected page.
+struct Scratch {
+    hmac: HmacSha256,
+    /// Current HKDF block `T(i)`, or PBKDF2's running `U_j`.
+    block: [u8; DIGEST_LEN],
+    /// PBKDF2's accumulated `T_i = U_1 ^ ... ^ U_c`.
+    acc: [u8; DIGEST_LEN],
+}
+
+/// A read-write protected page holding a zeroed [`Scratch`]. It is wiped
+/// and released when dropped.
+fn scratch() -> Result<Cell<Scratch>, MemoryError> {
+    let mut cell = Cell::new(Scratch {
+        hmac: HmacSha256::zeroed(),
+        block: [0; DIGEST_LEN],
+        acc: [0; DIGEST_LEN],
+    })?;
+    cell.read_write()?;
+    Ok(cell)
+}
+
+impl Scratch {
+    /// RFC 5869 section 2.3. `out.len()` must already be checked.
+    fn expand(&mut self, prk: &[u8], info: &[u8], out: &mut [u8]) {
+        self.hmac.init(prk);
+        for (i, chunk) in out.chunks_mut(DIGEST_LEN).enumerate() {
+            if i > 0 {
+                self.hmac.update(&self.block);
+            }
+            self.hmac.update(info);
+            self.hmac.update(&[i as u8 + 1]);
+            self.hmac.finish(&mut self.block);
+            chunk.copy_from_slice(&self.block[..chunk.len()]);
+        }
+    }
+
+    /// RFC 8018 section 5.2.
+    fn pbkdf2(&mut self, password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+        self.hmac.init(password);
+        for (i, chunk) in out.chunks_mut(DIGEST_LEN).enumerate() {
+            self.hmac.update(salt);
+            self.hmac.update(&(i as u32 + 1).to_be_bytes());
+            self.hmac.finish(&mut self.block);
+            self.acc = self.block;
+            for _ in 1..iterations {
+                self.hmac.update(&self.block);
+                self.hmac.finish(&mut self.block);
+                for (a, b) in self.acc.iter_mut().zip(&self.block) {
+                    *a ^= b;
+                }
+            }
+            chunk.copy_from_slice(&self.acc[..chunk.len()]);
+        }
+    }
+}
+
+fn check_expand(prk: &[u8], len: usize) -> Result<(), MemoryError> {
+    if prk.len() < DIGEST_LEN {
+        return Err(invalid("HKDF pseudorandom key must be at least 32 bytes"));
+    }
+    if len > HKDF_MAX_LEN {
+        return Err(invalid("HKDF output is limited to 8160 bytes"));
+    }
+    Ok(())
+}
+
+fn check_iterations(iterations: u32) -> Result<(), MemoryError> {
+    if iterations == 0 {
+        return Err(invalid("PBKDF2 needs at least one iteration"));
+    }
+    Ok(())
+}
+
+fn invalid(msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+}
@@ -1,13 +1,54 @@
 use error::MemoryError;
 
+#[cfg(feature = "aead")]
+pub mod aead;
+#[cfg(target_os = "linux")]
+pub mod agent;
+pub mod capability;
 mod cell;
+mod chacha;
+#[cfg(unix)]
+pub mod command;
+mod ct;
+pub mod encoding;
+pub mod encrypted;
+#[cfg(unix)]
+pub mod env;
 pub mod error;
 mod ffi;
+#[cfg(unix)]
+pub mod file;
+pub mod fork;
+pub mod generate;
+pub mod integrity;
+pub mod kdf;
 mod mem_safe;
+#[cfg(target_os = "linux")]
+pub mod memfd;
+pub mod pem;
+#[cfg(feature = "aead")]
+mod poly1305;
+mod process_key;
+#[cfg(unix)]
+pub mod prompt;
 mod ptr_ops;
+mod reader;
+pub mod rng;
 mod secret;
+mod secret_bytes;
+mod secret_string;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod sha256;
+pub mod shamir;
+pub mod split;
 #[cfg(feature = "type-state")]
 pub mod type_state;
+#[cfg(target_os = "linux")]
+pub mod vault;
+mod writer;
 
 pub use mem_safe::{MemSafe, MemSafeRead, MemSafeWrite};
 pub use secret::Secret;
+pub use secret_bytes::SecretBytes;
+pub use secret_string::SecretString;
@@ -37,11 +37,11 @@ use crate::MemoryError;
 // No `Debug`: this crate withholds `Debug` from every type that participates
 // in handling secret memory, so nothing about the protected region can leak
 // through a formatting macro.
-pub struct MemSafe<T> {
+pub struct MemSafe<T: ?Sized> {
     pub(crate) cell: Cell<T>,
 }
 
-unsafe impl<T> Send for MemSafe<T> where T: Send {}
+unsafe impl<T: ?Sized> Send for MemSafe<T> where T: Send {}
 
 impl<T> MemSafe<T> {
     /// Initialize a protected memory region containing the specified value,
@@ -69,7 +69,9 @@ impl<T> MemSafe<T> {
             cell: Cell::new(value)?,
         })
     }
+}
 
+impl<T: ?Sized> MemSafe<T> {
     /// Obtains read-only access to the protected memory region. This method temporarily
     /// elevates the read privileges and returns a handle that implements `Deref` for
     /// accessing the inner value. When the returned `MemSafeRead` is dropped,
@@ -98,11 +100,11 @@ impl<T> MemSafe<T> {
     }
 }
 
-pub struct MemSafeRead<'a, T> {
+pub struct MemSafeRead<'a, T: ?Sized> {
     mem_safe: &'a mut MemSafe<T>,
 }
 
-impl<T> Deref for MemSafeRead<'_, T> {
+impl<T: ?Sized> Deref for MemSafeRead<'_, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -110,17 +112,17 @@ impl<T> Deref for MemSafeRead<'_, T> {
     }
 }
 
-impl<T> Drop for MemSafeRead<'_, T> {
+impl<T: ?Sized> Drop for MemSafeRead<'_, T> {
     fn drop(&mut self) {
         self.mem_safe.cell.low_priv().unwrap();
     }
 }
 
-pub struct MemSafeWrite<'a, T> {
+pub struct MemSafeWrite<'a, T: ?Sized> {
     mem_safe: &'a mut MemSafe<T>,
 }
 
-impl<T> Deref for MemSafeWrite<'_, T> {
+impl<T: ?Sized> Deref for MemSafeWrite<'_, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -128,13 +130,13 @@ impl<T> Deref for MemSafeWrite<'_, T> {
     }
 }
 
-impl<T> DerefMut for MemSafeWrite<'_, T> {
+impl<T: ?Sized> DerefMut for MemSafeWrite<'_, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.mem_safe.cell.deref_mut()
     }
 }
 
-impl<T> Drop for MemSafeWrite<'_, T> {
+impl<T: ?Sized> Drop for MemSafeWrite<'_, T> {
     fn drop(&mut self) {
         self.mem_safe.cell.low_priv().unwrap();
     }
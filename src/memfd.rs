@@ -0,0 +1,312 @@
+//! Handing secrets to another process through sealed memfds.
+//!
+//! A privilege-separated daemon often loads keys in one process and uses
+//! them in another. Writing the key to a socket copies it through kernel
+//! socket buffers. [`send`] instead writes it into a fresh `memfd`, seals
+//! the memfd's size and contents, and passes only the descriptor over a
+//! Unix socket with `SCM_RIGHTS`. [`recv`] / [`recv_bytes`] map the memfd
+//! locked and dump-excluded, copy it into a new secret, and answer with a
+//! one-byte acknowledgement. The sender then wipes the memfd and both
+//! sides have closed their descriptors.
+//!
+//! ```
+//! use std::os::unix::net::UnixStream;
+//! use memsafe::{Secret, memfd};
+//!
+//! let (loader, worker) = UnixStream::pair().unwrap();
+//! let sender = std::thread::spawn(move || {
+//!     let mut key = Secret::<32>::random().unwrap();
+//!     memfd::send(&loader, &mut key).unwrap();
+//! });
+//! let mut key: Secret<32> = memfd::recv(&worker).unwrap();
+//! sender.join().unwrap();
+//! # let _ = key.read().unwrap();
+//! ```
+//!
+//! The memfd carries `F_SEAL_SHRINK`, `F_SEAL_GROW`, `F_SEAL_FUTURE_WRITE`
+//! and `F_SEAL_SEAL` (Linux 5.1+), and the receiver refuses descriptors
+//! without them: it cannot be made to fault on a truncated mapping or read
+//! contents rewritten through a new mapping. Only the sender's own mapping,
+//! made before sealing, stays writable, so that it can wipe the memfd.
+//! Linux only.
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+use crate::command::SecretSource;
+use crate::ptr_ops::secure_zero;
+use crate::{MemoryError, Secret, SecretBytes};
+
+const SEALS: libc::c_int =
+    libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_FUTURE_WRITE | libc::F_SEAL_SEAL;
+
+/// The data byte that carries the descriptor.
+const HANDOFF: u8 = b'M';
+/// The receiver's answers.
+const ACCEPTED: u8 = 1;
+const REJECTED: u8 = 0;
+
+/// Send `secret` over `socket` in a sealed memfd and wait for the peer to
+/// acknowledge it.
+///
+/// The secret is written into the memfd with `write(2)` straight from its
+/// page, and the memfd is mapped locked and dump-excluded while it holds
+/// the secret. When the peer answers — or the exchange fails — the memfd
+/// is wiped through that mapping and unmapped.
+///
+/// Blocks until the peer answers; set a read timeout on `socket` to bound
+/// the wait.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` wrapping the failing system call's error, one
+/// of kind `UnexpectedEof` if the peer closes the socket without answering,
+/// or one of kind `InvalidData` if the peer rejects the secret.
+pub fn send<S: SecretSource + ?Sized>(
+    socket: &UnixStream,
+    secret: &mut S,
+) -> Result<(), MemoryError> {
+    let flags = libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING;
+    let fd = unsafe { libc::memfd_create(c"memsafe-secret".as_ptr(), flags) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let memfd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let len = secret.secret_len();
+    if unsafe { libc::ftruncate(fd, len as libc::off_t) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // Mapped and locked before the secret arrives, so its pages are never
+    // swappable; wiped and unmapped on every path out of this function.
+    let _mapping = Mapping::new(fd, len, libc::PROT_READ | libc::PROT_WRITE)?;
+    secret.write_to_pipe(fd)?;
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, SEALS) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    send_fd(socket, memfd.as_raw_fd())?;
+    drop(memfd);
+
+    let mut answer = [0u8; 1];
+    loop {
+        match (&*socket).read(&mut answer) {
+            Ok(0) => return Err(error(ErrorKind::UnexpectedEof, "peer closed the socket")),
+            Ok(_) if answer[0] == ACCEPTED => return Ok(()),
+            Ok(_) => return Err(error(ErrorKind::InvalidData, "peer rejected the secret")),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Receive a secret of at most `N` bytes sent with [`send`], zero-padded.
+///
+/// # Errors
+///
+/// As for [`recv_bytes`], with `max` being `N`.
+pub fn recv<const N: usize>(socket: &UnixStream) -> Result<Secret<N>, MemoryError> {
+    receive(socket, N, |bytes| {
+        Secret::try_new_with(|page| {
+            page[..bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        })
+    })
+}
+
+/// Receive a secret of at most `max` bytes sent with [`send`].
+///
+/// The memfd is mapped read-only, locked and dump-excluded, copied into a
+/// new page, and unmapped; its descriptor is closed before the
+/// acknowledgement goes out. A memfd that is refused is answered too, so
+/// the sender does not wait forever.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` wrapping the failing system call's error, one
+/// of kind `UnexpectedEof` if the socket is closed, one of kind
+/// `InvalidData` if the message carries no descriptor or the descriptor is
+/// not a sealed memfd, or one of kind `InvalidInput` if the memfd is empty
+/// or larger than `max`.
+pub fn recv_bytes(socket: &UnixStream, max: usize) -> Result<SecretBytes, MemoryError> {
+    receive(socket, max, |bytes| {
+        SecretBytes::try_new_with(bytes.len(), |page| {
+            page.copy_from_slice(bytes);
+            Ok(())
+        })
+    })
+}
+
+fn receive<T>(
+    socket: &UnixStream,
+    max: usize,
+    copy: impl FnOnce(&[u8]) -> Result<T, MemoryError>,
+) -> Result<T, MemoryError> {
+    let fds = recv_fds(socket)?;
+    let result = match <[OwnedFd; 1]>::try_from(fds) {
+        Ok([memfd]) => check_sealed(&memfd, max).and_then(|len| {
+            let mapping = Mapping::new(memfd.as_raw_fd(), len, libc::PROT_READ)?;
+            copy(mapping.bytes())
+        }),
+        Err(_) => Err(error(
+            ErrorKind::InvalidData,
+            "message does not carry one memfd",
+        )),
+    };
+
+    let answer = if result.is_ok() { ACCEPTED } else { REJECTED };
+    (&*socket).write_all(&[answer])?;
+    result
+}
+
+/// The size of `memfd` if it carries [`SEALS`] and fits in `max` bytes.
+fn check_sealed(memfd: &OwnedFd, max: usize) -> Result<usize, MemoryError> {
+    let seals = unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_GET_SEALS) };
+    if seals < 0 || seals & SEALS != SEALS {
+        return Err(error(
+            ErrorKind::InvalidData,
+            "descriptor is not a sealed memfd",
+        ));
+    }
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(memfd.as_raw_fd(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let len = stat.st_size as usize;
+    if len == 0 || len > max {
+        return Err(error(ErrorKind::InvalidInput, "memfd size out of range"));
+    }
+    Ok(len)
+}
+
+/// A shared mapping of a memfd, locked and dump-excluded. Writable
+/// mappings are wiped before they are unmapped.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+    writable: bool,
+}
+
+impl Mapping {
+    fn new(fd: RawFd, len: usize, prot: libc::c_int) -> Result<Self, MemoryError> {
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let mapping = Mapping {
+            ptr: ptr.cast(),
+            len,
+            writable: prot & libc::PROT_WRITE != 0,
+        };
+        if unsafe { libc::mlock(ptr, len) } != 0
+            || unsafe { libc::madvise(ptr, len, libc::MADV_DONTDUMP) } != 0
+        {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(mapping)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.writable {
+            secure_zero(unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) });
+        }
+        unsafe {
+            libc::munlock(self.ptr.cast(), self.len);
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// Send `fd` over `socket` as `SCM_RIGHTS` ancillary data on a one-byte
+/// message.
+fn send_fd(socket: &UnixStream, fd: RawFd) -> Result<(), MemoryError> {
+    let mut byte = [HANDOFF];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let fd_len = std::mem::size_of::<RawFd>() as libc::c_uint;
+    let mut control = [0u64; 4];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(fd_len) } as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_len) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+    }
+    loop {
+        if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } >= 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err.into());
+        }
+    }
+}
+
+/// Receive one message from `socket` and the descriptors it carries. A
+/// message that is not a handoff, or whose descriptors were truncated,
+/// yields none; the descriptors it did carry are closed.
+fn recv_fds(socket: &UnixStream) -> Result<Vec<OwnedFd>, MemoryError> {
+    let mut byte = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut control = [0u64; 8];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+    let n = loop {
+        let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+        if n >= 0 {
+            break n;
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err.into());
+        }
+    };
+    if n == 0 {
+        return Err(error(ErrorKind::UnexpectedEof, "peer closed the socket"));
+    }
+
+    let mut fds = Vec::new();
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                    / std::mem::size_of::<RawFd>();
+                for i in 0..count {
+                    fds.push(OwnedFd::from_raw_fd(std::ptr::read_unaligned(data.add(i))));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if byte[0] != HANDOFF || msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        fds.clear();
+    }
+    Ok(fds)
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
@@ -0,0 +1,213 @@
+//! Poly1305 one-time authenticator (RFC 8439 section 2.5), dependency-free.
+//!
+//! A port of the 32-bit "donna" arithmetic: the accumulator and the clamped
+//! key are held in 26-bit limbs. As with the other primitives here, the
+//! products of each block multiply live in the state struct rather than in
+//! function locals.
+
+use crate::ptr_ops::ptr_fill_zero;
+
+/// Tag length in bytes.
+pub(crate) const TAG_LEN: usize = 16;
+
+const MASK26: u32 = 0x3ff_ffff;
+
+pub(crate) struct Poly1305 {
+    r: [u32; 5],
+    h: [u32; 5],
+    pad: [u32; 4],
+    d: [u64; 5],
+    buffer: [u8; 16],
+    /// Bytes of `buffer` filled so far.
+    leftover: usize,
+}
+
+fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+impl Poly1305 {
+    pub(crate) const fn zeroed() -> Self {
+        Poly1305 {
+            r: [0; 5],
+            h: [0; 5],
+            pad: [0; 4],
+            d: [0; 5],
+            buffer: [0; 16],
+            leftover: 0,
+        }
+    }
+
+    /// Load the one-time key `key` (`r || s`) and clear the accumulator.
+    pub(crate) fn init(&mut self, key: &[u8; 32]) {
+        self.r[0] = le32(&key[0..]) & 0x3ff_ffff;
+        self.r[1] = (le32(&key[3..]) >> 2) & 0x3ff_ff03;
+        self.r[2] = (le32(&key[6..]) >> 4) & 0x3ff_c0ff;
+        self.r[3] = (le32(&key[9..]) >> 6) & 0x3f0_3fff;
+        self.r[4] = (le32(&key[12..]) >> 8) & 0x00f_ffff;
+        for (p, chunk) in self.pad.iter_mut().zip(key[16..].chunks_exact(4)) {
+            *p = le32(chunk);
+        }
+        self.h = [0; 5];
+        self.leftover = 0;
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        if self.leftover > 0 {
+            let take = (16 - self.leftover).min(data.len());
+            self.buffer[self.leftover..self.leftover + take].copy_from_slice(&data[..take]);
+            self.leftover += take;
+            data = &data[take..];
+            if self.leftover < 16 {
+                return;
+            }
+            block(&self.r, &mut self.h, &mut self.d, &self.buffer, 1 << 24);
+            self.leftover = 0;
+        }
+        let mut blocks = data.chunks_exact(16);
+        for m in &mut blocks {
+            block(&self.r, &mut self.h, &mut self.d, m, 1 << 24);
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.leftover = rest.len();
+    }
+
+    /// Absorb zeros up to the next 16-byte boundary, as the AEAD
+    /// construction pads the associated data and the ciphertext.
+    pub(crate) fn pad_to_block(&mut self) {
+        if self.leftover > 0 {
+            self.update(&[0; 16][self.leftover..]);
+        }
+    }
+
+    /// Write the tag to `out`. The state must be re-initialised before it
+    /// is used again.
+    pub(crate) fn finish(&mut self, out: &mut [u8; TAG_LEN]) {
+        if self.leftover > 0 {
+            self.buffer[self.leftover] = 1;
+            self.buffer[self.leftover + 1..].fill(0);
+            block(&self.r, &mut self.h, &mut self.d, &self.buffer, 0);
+        }
+
+        // Fully carry h.
+        let h = &mut self.h;
+        for i in 1..5 {
+            h[i] += h[i - 1] >> 26;
+            h[i - 1] &= MASK26;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= MASK26;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK26;
+
+        // Compute g = h + 5 - 2^130 and select it over h, without a branch,
+        // if it did not underflow.
+        let mut g = [0u32; 5];
+        let mut carry = 5;
+        for (g, h) in g.iter_mut().zip(h.iter()) {
+            *g = h + carry;
+            carry = *g >> 26;
+            *g &= MASK26;
+        }
+        g[4] = g[4].wrapping_add(carry << 26).wrapping_sub(1 << 26);
+        let select_g = (g[4] >> 31).wrapping_sub(1);
+        for (h, g) in h.iter_mut().zip(&g) {
+            *h = (*h & !select_g) | (g & select_g);
+        }
+        ptr_fill_zero(&mut g as *mut [u32; 5]);
+
+        // h = (h + s) mod 2^128
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut f = 0u64;
+        for ((chunk, word), pad) in out.chunks_exact_mut(4).zip(words).zip(self.pad) {
+            f = word as u64 + pad as u64 + (f >> 32);
+            chunk.copy_from_slice(&(f as u32).to_le_bytes());
+        }
+    }
+}
+
+/// `h = (h + block) * r mod 2^130 - 5`, with `hibit` set for full
+/// blocks and clear for a padded final block. Takes the fields it needs
+/// separately so `m` can borrow the state's own buffer.
+fn block(r: &[u32; 5], h: &mut [u32; 5], d: &mut [u64; 5], m: &[u8], hibit: u32) {
+    let [r0, r1, r2, r3, r4] = *r;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+    h[0] += le32(&m[0..]) & MASK26;
+    h[1] += (le32(&m[3..]) >> 2) & MASK26;
+    h[2] += (le32(&m[6..]) >> 4) & MASK26;
+    h[3] += (le32(&m[9..]) >> 6) & MASK26;
+    h[4] += (le32(&m[12..]) >> 8) | hibit;
+
+    let mul = |a: u32, b: u32| a as u64 * b as u64;
+    d[0] = mul(h[0], r0) + mul(h[1], s4) + mul(h[2], s3) + mul(h[3], s2) + mul(h[4], s1);
+    d[1] = mul(h[0], r1) + mul(h[1], r0) + mul(h[2], s4) + mul(h[3], s3) + mul(h[4], s2);
+    d[2] = mul(h[0], r2) + mul(h[1], r1) + mul(h[2], r0) + mul(h[3], s4) + mul(h[4], s3);
+    d[3] = mul(h[0], r3) + mul(h[1], r2) + mul(h[2], r1) + mul(h[3], r0) + mul(h[4], s4);
+    d[4] = mul(h[0], r4) + mul(h[1], r3) + mul(h[2], r2) + mul(h[3], r1) + mul(h[4], r0);
+
+    let mut carry = 0u64;
+    for (h, d) in h.iter_mut().zip(d.iter_mut()) {
+        *d += carry;
+        carry = *d >> 26;
+        *h = *d as u32 & MASK26;
+    }
+    h[0] += carry as u32 * 5;
+    h[1] += h[0] >> 26;
+    h[0] &= MASK26;
+}
+
+impl Drop for Poly1305 {
+    fn drop(&mut self) {
+        ptr_fill_zero(self as *mut Self);
+    }
+}
+
+/// Test vectors from RFC 8439 section 2.5.2, plus a reduction edge case.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(key: &[u8; 32], msg: &[u8], split: usize) -> [u8; 16] {
+        let mut state = Poly1305::zeroed();
+        state.init(key);
+        state.update(&msg[..split]);
+        state.update(&msg[split..]);
+        let mut out = [0; 16];
+        state.finish(&mut out);
+        out
+    }
+
+    #[test]
+    fn matches_rfc8439_section_2_5_2() {
+        let key = [
+            0x85, 0xd6, 0xbe, 0x78, 0x57, 0x55, 0x6d, 0x33, 0x7f, 0x44, 0x52, 0xfe, 0x42, 0xd5,
+            0x06, 0xa8, 0x01, 0x03, 0x80, 0x8a, 0xfb, 0x0d, 0xb2, 0xfd, 0x4a, 0xbf, 0xf6, 0xaf,
+            0x41, 0x49, 0xf5, 0x1b,
+        ];
+        let expected = [
+            0xa8, 0x06, 0x1d, 0xc1, 0x30, 0x51, 0x36, 0xc6, 0xc2, 0x2b, 0x8b, 0xaf, 0x0c, 0x01,
+            0x27, 0xa9,
+        ];
+        let msg = b"Cryptographic Forum Research Group";
+        for split in [0, 5, 16, 20, msg.len()] {
+            assert_eq!(tag(&key, msg, split), expected);
+        }
+    }
+
+    #[test]
+    fn wraps_modulo_2_130_minus_5() {
+        // r = 2 and one block of 0xff: the accumulator ends just past
+        // 2^130 - 5, so only the final reduction yields the right tag.
+        let mut key = [0u8; 32];
+        key[0] = 2;
+        let mut expected = [0u8; 16];
+        expected[0] = 3;
+        assert_eq!(tag(&key, &[0xff; 16], 0), expected);
+    }
+}
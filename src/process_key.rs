@@ -0,0 +1,42 @@
+//! Random per-process keys held in protected pages.
+
+use std::sync::{Mutex, PoisonError};
+
+use crate::ffi::fill_random;
+use crate::{MemSafe, MemoryError};
+
+/// Length of a process key, in bytes.
+pub(crate) const KEY_LEN: usize = 32;
+
+/// A random key created on first use and kept in its own protected page.
+/// Each use of process keys in the crate owns a separate `static` of this
+/// type, so no two purposes share a key.
+pub(crate) struct ProcessKey {
+    slot: Mutex<Option<MemSafe<[u8; KEY_LEN]>>>,
+}
+
+impl ProcessKey {
+    pub(crate) const fn new() -> Self {
+        ProcessKey {
+            slot: Mutex::new(None),
+        }
+    }
+
+    /// Run `f` with read access to the key, creating it if needed.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&[u8; KEY_LEN]) -> R) -> Result<R, MemoryError> {
+        let mut slot = self.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = match slot.as_mut() {
+            Some(key) => key,
+            None => slot.insert(MemSafe::new([0u8; KEY_LEN])?),
+        };
+        // An all-zero key means either first use or a forked child, whose copy
+        // of the key page the kernel wiped (`MADV_WIPEONFORK`). Either way this
+        // process needs a key of its own.
+        let needs_key = key.read()?.iter().all(|&b| b == 0);
+        if needs_key {
+            fill_random(&mut key.write()?[..])?;
+        }
+        let view = key.read()?;
+        Ok(f(&view))
+    }
+}
@@ -0,0 +1,421 @@
+//! Reading passphrases from the terminal without echo.
+//!
+//! [`password`] opens the controlling terminal (`/dev/tty`, so it works
+//! when stdin and stdout are redirected), turns echo off, and reads the
+//! keystrokes one at a time straight into a locked staging page. Nothing
+//! passes through std's stdin buffer or a heap `String`. The result is
+//! copied into a [`SecretString`] of the exact length and the staging page
+//! is wiped.
+//!
+//! ```no_run
+//! let mut passphrase = memsafe::prompt::password("Passphrase: ").unwrap();
+//! # let _ = passphrase.read().unwrap();
+//! ```
+//!
+//! [`Prompt`] adds a confirmation entry, a length limit, byte output and an
+//! alternative terminal path:
+//!
+//! ```no_run
+//! use memsafe::prompt::Prompt;
+//!
+//! let mut key = Prompt::new("New passphrase: ")
+//!     .confirm("Repeat passphrase: ")
+//!     .max_len(256)
+//!     .read_bytes()
+//!     .unwrap();
+//! # let _ = key.read().unwrap();
+//! ```
+//!
+//! Backspace removes the last character, Ctrl-U clears the line, Enter
+//! ends the entry and Ctrl-D on an empty line aborts it. Other control
+//! characters are ignored.
+//!
+//! The saved terminal settings are restored when the prompt returns, when
+//! it unwinds from a panic, and when `SIGINT`, `SIGTERM`, `SIGHUP` or
+//! `SIGQUIT` arrives while it waits; the signal is then re-raised with the
+//! previous disposition. If that does not end the process, because the
+//! signal is ignored or its handler returns, the prompt fails with
+//! `ErrorKind::Interrupted` rather than read on with echo back on. A
+//! `SIGKILL`, or a panic under `panic = "abort"`, leaves echo off;
+//! `stty sane` recovers the terminal.
+
+use std::cell::UnsafeCell;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::cell::Cell;
+use crate::ct;
+use crate::{MemoryError, SecretBytes, SecretString};
+
+/// Longest entry accepted unless [`Prompt::max_len`] says otherwise.
+pub const DEFAULT_MAX_LEN: usize = 1024;
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+const CTRL_D: u8 = 0x04;
+const CTRL_U: u8 = 0x15;
+
+/// Signals whose default action would leave the terminal without echo.
+const SIGNALS: [libc::c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+/// Prompt for a passphrase on `/dev/tty` and return it as a
+/// [`SecretString`].
+///
+/// # Errors
+///
+/// As for [`Prompt::read_string`].
+pub fn password(prompt: &str) -> Result<SecretString, MemoryError> {
+    Prompt::new(prompt).read_string()
+}
+
+/// A configurable no-echo terminal prompt.
+pub struct Prompt {
+    text: String,
+    confirm: Option<String>,
+    max_len: usize,
+    tty: PathBuf,
+}
+
+impl Prompt {
+    /// A prompt that shows `text` on `/dev/tty`, with no confirmation and
+    /// a limit of [`DEFAULT_MAX_LEN`] bytes.
+    pub fn new(text: &str) -> Self {
+        Prompt {
+            text: text.to_owned(),
+            confirm: None,
+            max_len: DEFAULT_MAX_LEN,
+            tty: PathBuf::from("/dev/tty"),
+        }
+    }
+
+    /// Ask a second time with `text` and require both entries to match.
+    /// The comparison runs in constant time.
+    pub fn confirm(mut self, text: &str) -> Self {
+        self.confirm = Some(text.to_owned());
+        self
+    }
+
+    /// Reject entries longer than `max_len` bytes.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Use the terminal at `path` instead of `/dev/tty`, for example the
+    /// slave side of a pseudo-terminal.
+    pub fn tty(mut self, path: impl AsRef<Path>) -> Self {
+        self.tty = path.as_ref().to_owned();
+        self
+    }
+
+    /// Read the entry as UTF-8 text.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Prompt::read_bytes`], plus a `MemoryError` of kind
+    /// `InvalidData` if the entry is not valid UTF-8.
+    pub fn read_string(&self) -> Result<SecretString, MemoryError> {
+        let mut staging = self.read_staged()?;
+        let len = staging.len;
+        staging.page.read_only()?;
+        SecretString::new_with(len, |page| page.copy_from_slice(&staging.page[..len]))
+    }
+
+    /// Read the entry as raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `io::Error` from opening or configuring the terminal
+    /// wrapped in a `MemoryError`, or a `MemoryError` of kind:
+    /// - `UnexpectedEof` if input ends or Ctrl-D is pressed on an empty
+    ///   line;
+    /// - `InvalidInput` if the entry is empty or longer than the limit;
+    /// - `InvalidData` if the confirmation does not match.
+    pub fn read_bytes(&self) -> Result<SecretBytes, MemoryError> {
+        let mut staging = self.read_staged()?;
+        let len = staging.len;
+        staging.page.read_only()?;
+        SecretBytes::new_with(len, |page| page.copy_from_slice(&staging.page[..len]))
+    }
+
+    /// Run the prompt (and its confirmation) and return the entry in a
+    /// sealed staging page.
+    fn read_staged(&self) -> Result<Staged, MemoryError> {
+        // One prompt at a time: the signal handler restores a single saved
+        // state.
+        let _serial = PROMPT_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&self.tty)?;
+        let _echo_off = EchoOff::new(tty.as_raw_fd())?;
+
+        let mut first = read_entry(&mut tty, &self.text, self.max_len)?;
+        let Some(confirm) = &self.confirm else {
+            return Ok(first);
+        };
+        let mut second = read_entry(&mut tty, confirm, self.max_len)?;
+        first.page.read_only()?;
+        second.page.read_only()?;
+        if !ct::eq(&first.page[..first.len], &second.page[..second.len]) {
+            return Err(error(ErrorKind::InvalidData, "entries do not match"));
+        }
+        Ok(first)
+    }
+}
+
+/// An entry in a locked page one byte longer than the limit, so an
+/// overlong entry is detected without reading past the page.
+struct Staged {
+    page: Cell<[u8]>,
+    len: usize,
+}
+
+/// Show `text` and read one line into a new staging page.
+fn read_entry(tty: &mut File, text: &str, max_len: usize) -> Result<Staged, MemoryError> {
+    let page_len = max_len
+        .checked_add(1)
+        .ok_or_else(|| error(ErrorKind::InvalidInput, "maximum length too large"))?;
+    tty.write_all(text.as_bytes())?;
+    tty.flush()?;
+    let mut len = 0;
+    let page = Cell::<[u8]>::try_new_slice_with(page_len, |page| {
+        len = read_line(tty, page)?;
+        Ok(())
+    });
+    // Echo is off, so the user's Enter moved nothing.
+    tty.write_all(b"\n")?;
+    let page = page?;
+    if len == 0 {
+        return Err(error(ErrorKind::InvalidInput, "empty entry"));
+    }
+    Ok(Staged { page, len })
+}
+
+/// Read keystrokes into `page` until Enter, applying line editing, and
+/// return the entry length. The last slot of `page` only ever holds the
+/// byte being classified: an entry that would need it is overlong, and is
+/// reported once Enter is pressed so the rest of the line is not left for
+/// the next reader of the terminal.
+fn read_line(tty: &mut File, page: &mut [u8]) -> Result<usize, MemoryError> {
+    let max_len = page.len() - 1;
+    let mut len = 0;
+    let mut overlong = false;
+    loop {
+        wait_readable(tty.as_raw_fd())?;
+        match tty.read(&mut page[len..len + 1]) {
+            Ok(0) => return Err(error(ErrorKind::UnexpectedEof, "terminal closed")),
+            Ok(_) => {}
+            // Checked by `wait_readable` on the next pass.
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+        let byte = page[len];
+        match byte {
+            b'\n' | b'\r' => {
+                page[len] = 0;
+                if overlong {
+                    return Err(error(
+                        ErrorKind::InvalidInput,
+                        "entry exceeds maximum length",
+                    ));
+                }
+                return Ok(len);
+            }
+            BACKSPACE | DELETE => {
+                page[len] = 0;
+                // Drop one whole UTF-8 character: continuation bytes, then
+                // its lead byte.
+                while len > 0 {
+                    len -= 1;
+                    let removed = page[len];
+                    page[len] = 0;
+                    if removed & 0xc0 != 0x80 {
+                        break;
+                    }
+                }
+            }
+            CTRL_U => {
+                page[..=len].fill(0);
+                len = 0;
+                overlong = false;
+            }
+            CTRL_D if len == 0 => {
+                page[0] = 0;
+                return Err(error(ErrorKind::UnexpectedEof, "input aborted"));
+            }
+            _ if byte < 0x20 && byte != b'\t' => page[len] = 0,
+            _ if len == max_len => {
+                page[len] = 0;
+                overlong = true;
+            }
+            _ => len += 1,
+        }
+    }
+}
+
+/// Wait until `fd` has input.
+///
+/// Fails with `Interrupted` once a signal handler has restored the
+/// terminal: the signal did not end the process (it was ignored, or its
+/// handler returned), and echo is back on, so the prompt must stop
+/// reading. The check and the wait are one step with respect to
+/// [`SIGNALS`]: they stay blocked until `ppoll` atomically unblocks them,
+/// so a signal arriving after the check still interrupts the wait.
+fn wait_readable(fd: RawFd) -> Result<(), MemoryError> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        let mask = BlockSignals::new();
+        if ACTIVE_FD.load(Ordering::SeqCst) == -1 {
+            return Err(error(ErrorKind::Interrupted, "interrupted by a signal"));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let ret = unsafe { libc::ppoll(&mut pollfd, 1, std::ptr::null(), &mask.old) };
+        // Without `ppoll`, wake up regularly to repeat the check.
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let ret = {
+            drop(mask);
+            unsafe { libc::poll(&mut pollfd, 1, 100) }
+        };
+        match ret {
+            -1 => {
+                let e = std::io::Error::last_os_error();
+                if e.kind() != ErrorKind::Interrupted {
+                    return Err(e.into());
+                }
+            }
+            0 => {}
+            _ => return Ok(()),
+        }
+    }
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
+
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Descriptor of the terminal whose settings are in [`SAVED`], or -1.
+static ACTIVE_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Terminal settings and signal dispositions from before the prompt.
+/// Written under [`PROMPT_LOCK`] while `ACTIVE_FD` is -1; read only by
+/// whoever takes the descriptor out of `ACTIVE_FD`.
+static SAVED: Saved = Saved(UnsafeCell::new(None));
+
+type SavedState = (libc::termios, [libc::sigaction; SIGNALS.len()]);
+
+struct Saved(UnsafeCell<Option<SavedState>>);
+
+unsafe impl Sync for Saved {}
+
+/// Echo disabled on a terminal; restores the previous settings and signal
+/// handlers on drop.
+struct EchoOff {
+    fd: RawFd,
+}
+
+impl EchoOff {
+    fn new(fd: RawFd) -> Result<Self, MemoryError> {
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let mut quiet = saved;
+        // Byte-at-a-time reads with our own line editing; ISIG stays on so
+        // Ctrl-C still interrupts.
+        quiet.c_lflag &= !(libc::ECHO | libc::ECHONL | libc::ICANON | libc::IEXTEN);
+        quiet.c_cc[libc::VMIN] = 1;
+        quiet.c_cc[libc::VTIME] = 0;
+
+        // Hold the signals off until the handler has everything it needs.
+        let mask = BlockSignals::new();
+        let mut previous: [libc::sigaction; SIGNALS.len()] = unsafe { std::mem::zeroed() };
+        for (signal, previous) in SIGNALS.iter().zip(previous.iter_mut()) {
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = restore_and_reraise as extern "C" fn(libc::c_int) as usize;
+            unsafe {
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(*signal, &action, previous);
+            }
+        }
+        unsafe { *SAVED.0.get() = Some((saved, previous)) };
+        ACTIVE_FD.store(fd, Ordering::SeqCst);
+        let guard = EchoOff { fd };
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &quiet) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        drop(mask);
+        Ok(guard)
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        let _mask = BlockSignals::new();
+        // Already restored if a handler ran.
+        if ACTIVE_FD.swap(-1, Ordering::SeqCst) == self.fd {
+            restore(self.fd);
+        }
+    }
+}
+
+/// Signals in [`SIGNALS`] blocked for the current thread until drop.
+struct BlockSignals {
+    old: libc::sigset_t,
+}
+
+impl BlockSignals {
+    fn new() -> Self {
+        unsafe {
+            let mut set: libc::sigset_t = std::mem::zeroed();
+            let mut old: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for signal in SIGNALS {
+                libc::sigaddset(&mut set, signal);
+            }
+            libc::pthread_sigmask(libc::SIG_BLOCK, &set, &mut old);
+            BlockSignals { old }
+        }
+    }
+}
+
+impl Drop for BlockSignals {
+    fn drop(&mut self) {
+        unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &self.old, std::ptr::null_mut()) };
+    }
+}
+
+/// Put back the saved terminal settings and signal dispositions. Called
+/// only by whoever took `fd` out of [`ACTIVE_FD`]; async-signal-safe.
+fn restore(fd: RawFd) {
+    if let Some((termios, previous)) = unsafe { &*SAVED.0.get() } {
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, termios);
+            for (signal, previous) in SIGNALS.iter().zip(previous) {
+                libc::sigaction(*signal, previous, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+/// Restore the terminal, then deliver the signal again to whoever handled
+/// it before. The re-raised signal is blocked until this handler returns.
+extern "C" fn restore_and_reraise(signal: libc::c_int) {
+    let fd = ACTIVE_FD.swap(-1, Ordering::SeqCst);
+    if fd >= 0 {
+        restore(fd);
+    }
+    unsafe { libc::raise(signal) };
+}
@@ -34,14 +34,23 @@ pub fn secure_zero(slice: &mut [u8]) {
     compiler_fence(Ordering::SeqCst);
 }
 
-pub fn ptr_deref<'a, T>(ptr: *const T) -> &'a T {
+/// Volatile, compiler-fenced zeroization of the `len` bytes at `ptr`.
+///
+/// The byte-count counterpart of [`ptr_fill_zero`], for regions whose size
+/// is not `size_of::<T>()`: unsized values such as `[u8]`, or a whole
+/// mapping.
+pub fn ptr_fill_zero_bytes<T: ?Sized>(ptr: *mut T, len: usize) {
+    secure_zero(unsafe { std::slice::from_raw_parts_mut(ptr as *mut u8, len) });
+}
+
+pub fn ptr_deref<'a, T: ?Sized>(ptr: *const T) -> &'a T {
     unsafe { &*ptr }
 }
 
-pub fn ptr_deref_mut<'a, T>(ptr: *mut T) -> &'a mut T {
+pub fn ptr_deref_mut<'a, T: ?Sized>(ptr: *mut T) -> &'a mut T {
     unsafe { &mut *ptr }
 }
 
-pub fn ptr_drop_in_place<T>(ptr: *mut T) {
+pub fn ptr_drop_in_place<T: ?Sized>(ptr: *mut T) {
     unsafe { ptr.drop_in_place() };
 }
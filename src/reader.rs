@@ -0,0 +1,44 @@
+//! Reading secrets from `io::Read` sources straight into protected pages.
+
+use std::io::{ErrorKind, Read};
+
+use crate::MemoryError;
+use crate::ptr_ops::secure_zero;
+
+/// Read from `reader` until end of input, filling `buf` from the start, and
+/// return the number of bytes read.
+///
+/// Short reads and `Interrupted` errors are retried. If the input holds
+/// more than `buf.len()` bytes, one byte past the end is read into a
+/// wiped-after-use probe and an `InvalidInput` error is returned; the
+/// caller's page rollback wipes `buf`.
+pub(crate) fn read_to_fill<R: Read + ?Sized>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> Result<usize, MemoryError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(filled),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let mut probe = [0u8; 1];
+    loop {
+        let result = reader.read(&mut probe);
+        secure_zero(&mut probe);
+        match result {
+            Ok(0) => return Ok(filled),
+            Ok(_) => {
+                return Err(MemoryError::from(std::io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "input exceeds buffer size",
+                )));
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! A fork-safe CSPRNG whose state lives in a protected page.
+//!
+//! [`SecureRng`] is ChaCha20 keyed from the OS, with its whole state — key,
+//! input block, buffered keystream — in a locked, dump-excluded page that
+//! is `PROT_NONE` between calls. After every call the key is replaced with
+//! fresh keystream ("fast key erasure"), so a later compromise of the state
+//! does not reveal earlier output.
+//!
+//! Userspace RNGs repeat their parent's stream in a forked child unless
+//! something tells them to reseed. Here the page itself does: on Linux it
+//! is marked `MADV_WIPEONFORK`, so a child finds the state zeroed and
+//! reseeds from `getrandom` before producing anything. On other platforms
+//! the state records the owning process id, and a mismatch has the same
+//! effect.
+//!
+//! ```
+//! use memsafe::rng::SecureRng;
+//!
+//! let mut rng = SecureRng::new().unwrap();
+//! let mut nonce = [0u8; 12];
+//! rng.fill_bytes(&mut nonce).unwrap();
+//!
+//! // Keys can be drawn straight into a protected page.
+//! let mut key = rng.secret::<32>().unwrap();
+//! # let _ = key.read().unwrap();
+//! ```
+
+use crate::cell::Cell;
+use crate::chacha::ChaCha20State;
+use crate::ffi::fill_random;
+use crate::{MemoryError, Secret};
+
+/// Output drawn under one key before it is replaced, so the 32-bit block
+/// counter can never wrap within a call.
+const REKEY_INTERVAL: usize = 1 << 20;
+
+/// A cryptographically secure RNG that reseeds itself after `fork()`.
+///
+/// Like [`Secret`], it implements neither `Debug` nor `Display`.
+pub struct SecureRng {
+    state: Cell<State>,
+}
+
+// The state page is owned exclusively by this value, like `MemSafe`'s.
+unsafe impl Send for SecureRng {}
+
+struct State {
+    chacha: ChaCha20State,
+    key: [u8; 32],
+    /// Non-zero once seeded. The kernel zeroes it in a forked child.
+    seeded: u8,
+    /// Process that seeded the state, for platforms without wipe-on-fork.
+    pid: u32,
+}
+
+impl SecureRng {
+    /// Create an RNG seeded from the operating system's CSPRNG.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::Entropy`] if the OS cannot supply randomness,
+    /// or a protection error if the state page cannot be set up.
+    pub fn new() -> Result<Self, MemoryError> {
+        let mut rng = SecureRng {
+            state: Cell::new(State {
+                chacha: ChaCha20State::zeroed(),
+                key: [0; 32],
+                seeded: 0,
+                pid: 0,
+            })?,
+        };
+        rng.reseed()?;
+        Ok(rng)
+    }
+
+    /// Replace the state with a fresh seed from the OS.
+    pub fn reseed(&mut self) -> Result<(), MemoryError> {
+        self.state.read_write()?;
+        let result = self.state.reseed();
+        self.state.low_priv()?;
+        result
+    }
+
+    /// Fill `out` with random bytes.
+    pub fn fill_bytes(&mut self, out: &mut [u8]) -> Result<(), MemoryError> {
+        self.state.read_write()?;
+        let result = self.state.fill(out);
+        self.state.low_priv()?;
+        result
+    }
+
+    /// A uniformly random `u32`.
+    pub fn next_u32(&mut self) -> Result<u32, MemoryError> {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    /// A uniformly random `u64`.
+    pub fn next_u64(&mut self) -> Result<u64, MemoryError> {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// A new `N`-byte secret filled with random bytes directly in its page.
+    pub fn secret<const N: usize>(&mut self) -> Result<Secret<N>, MemoryError> {
+        Secret::try_new_with(|page| self.fill_bytes(page))
+    }
+}
+
+impl State {
+    fn reseed(&mut self) -> Result<(), MemoryError> {
+        fill_random(&mut self.key)?;
+        self.rekey();
+        self.seeded = 1;
+        self.pid = std::process::id();
+        Ok(())
+    }
+
+    /// Key ChaCha20 from `self.key`, then wipe `self.key`.
+    fn rekey(&mut self) {
+        self.chacha.init(&self.key, &[0; 12], 0);
+        self.key.fill(0);
+    }
+
+    fn fill(&mut self, out: &mut [u8]) -> Result<(), MemoryError> {
+        if self.seeded == 0 || self.pid != std::process::id() {
+            self.reseed()?;
+        }
+        for chunk in out.chunks_mut(REKEY_INTERVAL) {
+            chunk.fill(0);
+            self.chacha.apply_keystream(chunk);
+            // Fast key erasure: the next key comes from keystream that is
+            // never handed out, and the old key is gone.
+            self.chacha.apply_keystream(&mut self.key);
+            self.rekey();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The raw bytes of the state page.
+    fn state_bytes(rng: &mut SecureRng) -> Vec<u8> {
+        rng.state.read_only().unwrap();
+        let state: &State = &rng.state;
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                (state as *const State).cast::<u8>(),
+                std::mem::size_of::<State>(),
+            )
+        }
+        .to_vec();
+        rng.state.low_priv().unwrap();
+        bytes
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn state_after_fill_reveals_neither_output_nor_old_key() {
+        let mut rng = SecureRng::new().unwrap();
+        rng.state.read_only().unwrap();
+        let old_key = rng.state.chacha.key();
+        rng.state.low_priv().unwrap();
+
+        // Short enough that output and the next key share a keystream block.
+        let mut out = [0u8; 16];
+        rng.fill_bytes(&mut out).unwrap();
+        let page = state_bytes(&mut rng);
+        assert!(!contains(&page, &old_key), "previous key left in the state");
+        assert!(!contains(&page, &out), "output left in the state");
+    }
+}
@@ -1,6 +1,14 @@
+use std::io::{Read, Write};
+
 use crate::MemoryError;
 use crate::cell::Cell;
+use crate::ct;
+use crate::ffi::fill_random;
 use crate::mem_safe::{MemSafe, MemSafeRead, MemSafeWrite};
+use crate::reader::read_to_fill;
+use crate::writer::write_all_to;
+#[cfg(unix)]
+use crate::writer::write_all_to_fd;
 
 /// A fixed-size secret stored entirely *inline* within a protected memory page.
 ///
@@ -84,6 +92,39 @@ impl<const N: usize> Secret<N> {
         })
     }
 
+    /// Like [`Secret::new_with`], but `init` may fail. On `Err` the page is
+    /// wiped and released, and the error is returned.
+    pub(crate) fn try_new_with<F>(init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]) -> Result<(), MemoryError>,
+    {
+        Cell::<[u8; N]>::try_new_with(init).map(|cell| Secret {
+            inner: MemSafe { cell },
+        })
+    }
+
+    /// Allocate an `N`-byte secret filled from the operating system's
+    /// CSPRNG (`getrandom(2)` on Linux).
+    ///
+    /// The random bytes are written straight into the locked page, so keys
+    /// and nonces generated this way never pass through an unprotected
+    /// buffer.
+    ///
+    /// ```
+    /// use memsafe::Secret;
+    ///
+    /// let mut key = Secret::<32>::random().unwrap();
+    /// # let _ = key.read().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::Entropy`] if the OS cannot supply randomness,
+    /// or a protection error as for [`Secret::new_with`].
+    pub fn random() -> Result<Self, MemoryError> {
+        Self::try_new_with(|page| fill_random(page))
+    }
+
     /// Encapsulate an owned byte source into a new secret, volatile-zeroing
     /// the source after the copy.
     ///
@@ -105,14 +146,121 @@ impl<const N: usize> Secret<N> {
         })
     }
 
+    /// Read a secret of at most `N` bytes from `reader` until end of input,
+    /// straight into the locked page. Unused bytes stay zero.
+    ///
+    /// Short reads and `Interrupted` errors are retried.
+    ///
+    /// ```
+    /// use memsafe::Secret;
+    ///
+    /// let mut input: &[u8] = b"my-api-key";
+    /// let mut secret = Secret::<64>::from_reader(&mut input).unwrap();
+    /// assert_eq!(&secret.read().unwrap()[..10], b"my-api-key");
+    /// ```
+    ///
+    /// Bytes that a buffered reader held before handing them over are
+    /// beyond this crate's reach. `std::io::stdin()` is one such reader: it
+    /// buffers input on the heap. Prefer an unbuffered source, such as a
+    /// `File`, where possible.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reader's `io::Error` wrapped in a `MemoryError`, or a
+    /// `MemoryError` of kind `InvalidInput` if the input is longer than `N`
+    /// bytes. On any error the partially filled page is wiped and released.
+    pub fn from_reader<R: Read + ?Sized>(reader: &mut R) -> Result<Self, MemoryError> {
+        Self::try_new_with(|page| read_to_fill(reader, page).map(drop))
+    }
+
+    /// Read the file at `path` into a new secret of at most `N` bytes,
+    /// zero-padded, through its descriptor straight into the locked page.
+    ///
+    /// Shorthand for [`SecretFile::new(path).read()`](crate::file::SecretFile::read);
+    /// use [`SecretFile`](crate::file::SecretFile) to refuse files readable
+    /// by other users or to shred one-shot credential files.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SecretFile::read`](crate::file::SecretFile::read).
+    #[cfg(unix)]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, MemoryError> {
+        crate::file::SecretFile::new(path).read()
+    }
+
+    /// Run `command` and capture at most `N` bytes of its stdout into a
+    /// new secret, zero-padded, reading the pipe straight into the locked
+    /// page.
+    ///
+    /// Shorthand for
+    /// [`SecretCommand::new(command).read()`](crate::command::SecretCommand::read);
+    /// use [`SecretCommand`](crate::command::SecretCommand) to trim a
+    /// trailing newline or set a timeout.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SecretCommand::read`](crate::command::SecretCommand::read).
+    #[cfg(unix)]
+    pub fn from_command(command: std::process::Command) -> Result<Self, MemoryError> {
+        crate::command::SecretCommand::new(command).read()
+    }
+
+    /// Move the environment variable `name` into a new secret of at most
+    /// `N` bytes, zero-padded, and scrub it from the environment.
+    ///
+    /// The value is copied from the `environ` entry straight into the
+    /// locked page; no heap `String` is involved. The entry's value bytes
+    /// are then overwritten with zeros and the variable is unset. The
+    /// returned [`Scrub`](crate::env::Scrub) reports whether both steps
+    /// took effect.
+    ///
+    /// ```
+    /// use memsafe::Secret;
+    ///
+    /// # unsafe { std::env::set_var("DB_PASSWORD", "hunter2") };
+    /// // SAFETY: no other thread touches the environment yet.
+    /// let (mut password, scrub) = unsafe { Secret::<64>::from_env("DB_PASSWORD") }.unwrap();
+    /// assert_eq!(&password.read().unwrap()[..7], b"hunter2");
+    /// assert!(scrub.unset);
+    /// assert!(std::env::var_os("DB_PASSWORD").is_none());
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// As for [`std::env::remove_var`]: no other thread may read or write
+    /// the environment during the call, through `std::env` or through libc
+    /// functions such as `getenv`. Call it early in `main`, before spawning
+    /// threads.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `NotFound` if the variable is not
+    /// set, or of kind `InvalidInput` if `name` is empty or contains `=` or
+    /// NUL, or if the value is longer than `N` bytes. On error the
+    /// environment is left untouched.
+    #[cfg(unix)]
+    pub unsafe fn from_env(name: &str) -> Result<(Self, crate::env::Scrub), MemoryError> {
+        unsafe {
+            crate::env::take(name, |value| {
+                if value.len() > N {
+                    return Err(MemoryError::from(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "value exceeds secret size",
+                    )));
+                }
+                Self::new_with(|page| page[..value.len()].copy_from_slice(value))
+            })
+        }
+    }
+
     /// Obtain temporary read access to the secret bytes. The returned guard
     /// derefs to `&[u8; N]` and restores lowest-privilege access on drop
     /// (Unix).
     ///
     /// **Timing note:** comparing secret bytes with `==` is not
     /// constant-time and can leak information through timing side channels.
-    /// If you compare secrets (password checks, MAC verification), use a
-    /// constant-time comparison such as the `subtle` crate's `ct_eq`.
+    /// If you compare secrets (password checks, MAC verification), use
+    /// [`Secret::ct_eq`], [`Secret::ct_eq_slice`] or [`Secret::ct_eq_padded`].
     pub fn read(&mut self) -> Result<MemSafeRead<'_, [u8; N]>, MemoryError> {
         self.inner.read()
     }
@@ -121,6 +269,118 @@ impl<const N: usize> Secret<N> {
     pub fn write(&mut self) -> Result<MemSafeWrite<'_, [u8; N]>, MemoryError> {
         self.inner.write()
     }
+
+    /// Run `f` on the secret through a shared reference; the page is
+    /// readable only for the duration of the call.
+    #[cfg(feature = "serde")]
+    pub(crate) fn peek<R>(&self, f: impl FnOnce(&[u8; N]) -> R) -> Result<R, MemoryError> {
+        self.inner.cell.peek(f)
+    }
+
+    /// Write the secret bytes to `writer`, then flush it.
+    ///
+    /// The page is unsealed only while the writes run, and the bytes go
+    /// from the page to `writer.write` without a staging copy. Partial
+    /// writes and `Interrupted` errors are retried. A buffering writer
+    /// such as `BufWriter` copies the bytes into its own heap buffer, out
+    /// of this crate's reach; pass the unbuffered sink, or use
+    /// [`Secret::write_to_fd`].
+    ///
+    /// ```
+    /// use memsafe::Secret;
+    ///
+    /// let mut secret = Secret::<4>::new_with(|b| b.copy_from_slice(b"key!")).unwrap();
+    /// let mut sink = Vec::new();
+    /// secret.write_to(&mut sink).unwrap();
+    /// assert_eq!(sink, b"key!");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the writer's `io::Error` wrapped in a `MemoryError`, or a
+    /// `MemoryError` of kind `WriteZero` if the writer stops accepting
+    /// bytes. Some bytes may have been written by then.
+    pub fn write_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<(), MemoryError> {
+        write_all_to(writer, &self.read()?[..])
+    }
+
+    /// Write the secret bytes to the file descriptor `fd` with `write(2)`
+    /// directly, so no userspace buffer holds them on the way out.
+    ///
+    /// Partial writes and `EINTR` are retried; on a non-blocking descriptor
+    /// the call waits until it is writable. `fd` stays open.
+    ///
+    /// # Errors
+    ///
+    /// As for [`Secret::write_to`], with the `write(2)` error.
+    #[cfg(unix)]
+    pub fn write_to_fd(&mut self, fd: std::os::fd::RawFd) -> Result<(), MemoryError> {
+        write_all_to_fd(fd, &self.read()?[..])
+    }
+
+    /// Compare two secrets in constant time.
+    ///
+    /// Both pages are compared in place under read guards; neither operand
+    /// is copied out of protected memory.
+    pub fn ct_eq(&mut self, other: &mut Secret<N>) -> Result<bool, MemoryError> {
+        let a = self.read()?;
+        let b = other.read()?;
+        Ok(ct::eq(&a[..], &b[..]))
+    }
+
+    /// Compare the secret with `other` in constant time.
+    ///
+    /// `other` must be exactly `N` bytes long to compare equal. Lengths are
+    /// treated as public: a length mismatch returns `false` without reading
+    /// the secret. Use [`Secret::ct_eq_padded`] when the secret is a shorter
+    /// value zero-padded to `N` and its length must stay hidden.
+    pub fn ct_eq_slice(&mut self, other: &[u8]) -> Result<bool, MemoryError> {
+        if other.len() != N {
+            return Ok(false);
+        }
+        Ok(ct::eq(&self.read()?[..], other))
+    }
+
+    /// Compare the secret with `other` zero-padded to `N` bytes, in constant
+    /// time.
+    ///
+    /// This is the comparison for the usual `Secret<N>` layout, where a
+    /// shorter value such as a password is stored with its unused trailing
+    /// bytes left zero. The full `N` bytes are always compared, so the time
+    /// taken reveals `N` and `other.len()` but not the length of the stored
+    /// value. As with any zero-padded encoding, trailing zero bytes in
+    /// `other` are indistinguishable from padding.
+    pub fn ct_eq_padded(&mut self, other: &[u8]) -> Result<bool, MemoryError> {
+        Ok(ct::eq_padded(&self.read()?[..], other))
+    }
+
+    /// Overwrite this secret with `other` if `choice` is true, leaving it
+    /// unchanged otherwise. Both cases run the same instructions.
+    pub fn ct_assign(&mut self, other: &mut Secret<N>, choice: bool) -> Result<(), MemoryError> {
+        let mut dst = self.write()?;
+        let src = other.read()?;
+        ct::assign(&mut dst[..], &src[..], choice);
+        Ok(())
+    }
+
+    /// Swap the contents of two secrets if `choice` is true, leaving both
+    /// unchanged otherwise. Both cases run the same instructions.
+    pub fn ct_swap(&mut self, other: &mut Secret<N>, choice: bool) -> Result<(), MemoryError> {
+        let mut a = self.write()?;
+        let mut b = other.write()?;
+        ct::swap(&mut a[..], &mut b[..], choice);
+        Ok(())
+    }
+
+    /// XOR `other` into this secret in place.
+    pub fn xor_assign(&mut self, other: &mut Secret<N>) -> Result<(), MemoryError> {
+        let mut dst = self.write()?;
+        let src = other.read()?;
+        for (d, s) in dst.iter_mut().zip(src.iter()) {
+            *d ^= s;
+        }
+        Ok(())
+    }
 }
 
 impl<const N: usize> TryFrom<&str> for Secret<N> {
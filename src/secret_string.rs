@@ -0,0 +1,155 @@
+use std::io::Write;
+
+use crate::MemoryError;
+use crate::cell::Cell;
+use crate::mem_safe::{MemSafe, MemSafeRead, MemSafeWrite};
+use crate::ptr_ops::secure_zero;
+use crate::writer::write_all_to;
+#[cfg(unix)]
+use crate::writer::write_all_to_fd;
+
+/// A UTF-8 secret stored entirely inside a protected memory page.
+///
+/// `SecretString` is [`SecretBytes`](crate::SecretBytes) with the contents
+/// validated as UTF-8 at construction, so its guards deref to `str`. Use it
+/// for passwords and tokens that are handed on as text.
+///
+/// As with `SecretBytes`, the length is public and a zero-length secret is
+/// rejected.
+///
+/// # Examples
+///
+/// ```
+/// use memsafe::SecretString;
+///
+/// let mut secret = SecretString::try_from("correct horse").unwrap();
+/// assert_eq!(&*secret.read().unwrap(), "correct horse");
+/// ```
+pub struct SecretString {
+    inner: MemSafe<str>,
+    len: usize,
+}
+
+impl SecretString {
+    /// Allocate a `len`-byte secret, let `init` fill it in place, and
+    /// validate the result as UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidData` if `init` wrote invalid
+    /// UTF-8; the page is wiped and released first.
+    pub fn new_with<F>(len: usize, init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        Self::try_new_with(len, |page| {
+            init(page);
+            Ok(())
+        })
+    }
+
+    /// Like [`SecretString::new_with`], but `init` may fail. On `Err` the
+    /// page is wiped and released, and the error is returned.
+    pub(crate) fn try_new_with<F>(len: usize, init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<(), MemoryError>,
+    {
+        let cell = Cell::<[u8]>::try_new_slice_with(len, |page| {
+            init(page)?;
+            std::str::from_utf8(page).map_err(|_| {
+                MemoryError::from(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "secret is not valid UTF-8",
+                ))
+            })?;
+            Ok(())
+        })?;
+        Ok(SecretString {
+            inner: MemSafe {
+                cell: cell.into_str_unchecked(),
+            },
+            len,
+        })
+    }
+
+    /// Length of the secret in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`: zero-length secrets cannot be constructed.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Obtain temporary read access to the secret. The returned guard derefs
+    /// to `&str` and restores lowest-privilege access on drop (Unix).
+    pub fn read(&mut self) -> Result<MemSafeRead<'_, str>, MemoryError> {
+        self.inner.read()
+    }
+
+    /// Obtain temporary read-write access to the secret. The guard derefs to
+    /// `&mut str`, which only permits UTF-8-preserving edits.
+    pub fn write(&mut self) -> Result<MemSafeWrite<'_, str>, MemoryError> {
+        self.inner.write()
+    }
+
+    /// Run `f` on the secret through a shared reference; the page is
+    /// readable only for the duration of the call.
+    #[cfg(feature = "serde")]
+    pub(crate) fn peek<R>(&self, f: impl FnOnce(&str) -> R) -> Result<R, MemoryError> {
+        self.inner.cell.peek(f)
+    }
+
+    /// Write the secret bytes to `writer`, then flush it. See
+    /// [`Secret::write_to`](crate::Secret::write_to).
+    pub fn write_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<(), MemoryError> {
+        write_all_to(writer, self.read()?.as_bytes())
+    }
+
+    /// Write the secret bytes to the file descriptor `fd` with `write(2)`.
+    /// See [`Secret::write_to_fd`](crate::Secret::write_to_fd).
+    #[cfg(unix)]
+    pub fn write_to_fd(&mut self, fd: std::os::fd::RawFd) -> Result<(), MemoryError> {
+        write_all_to_fd(fd, self.read()?.as_bytes())
+    }
+}
+
+impl TryFrom<&str> for SecretString {
+    type Error = MemoryError;
+
+    /// Copy a borrowed string slice into a secret.
+    ///
+    /// **Note:** A borrowed `&str` cannot be zeroized by this crate. Prefer
+    /// [`TryFrom<String>`] (zeroes the source) when the input was generated
+    /// at runtime.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::new_with(s.len(), |page| page.copy_from_slice(s.as_bytes()))
+    }
+}
+
+impl TryFrom<String> for SecretString {
+    type Error = (String, MemoryError);
+
+    /// Move an owned `String` into a secret, volatile-zeroing the source.
+    /// On error the original `String` is returned alongside the reason.
+    ///
+    /// The capacity caveats of [`Secret::from_bytes`](crate::Secret::from_bytes)
+    /// apply here too.
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let mut bytes = s.into_bytes();
+        match Self::new_with(bytes.len(), |page| {
+            page.copy_from_slice(&bytes);
+            secure_zero(&mut bytes);
+        }) {
+            Ok(secret) => Ok(secret),
+            // Both paths produce valid UTF-8: the empty-input case never
+            // mutated the bytes, and the memory-error case zeroed them
+            // (NUL is valid UTF-8).
+            Err(e) => Err((
+                String::from_utf8(bytes).expect("zeroed or original UTF-8 bytes"),
+                e,
+            )),
+        }
+    }
+}
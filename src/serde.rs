@@ -0,0 +1,294 @@
+//! `serde` support: deserializing straight into protected memory.
+//!
+//! With the `serde` feature, [`Secret<N>`], [`SecretBytes`] and
+//! [`SecretString`] implement `Deserialize`. Input borrowed from the
+//! deserializer (`&str` / `&[u8]`) is copied straight into a locked page.
+//! Owned input (`visit_string`, `visit_byte_buf`) is copied the same way
+//! and then wiped, but it was already on the heap before the visitor saw
+//! it; see the caveat below for what that means for common formats.
+//!
+//! ```
+//! use memsafe::{Secret, SecretString};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Config {
+//!     user: String,
+//!     #[serde(serialize_with = "memsafe::serde::expose")]
+//!     password: SecretString,
+//!     #[serde(serialize_with = "memsafe::serde::expose")]
+//!     key: Secret<4>,
+//! }
+//!
+//! let json = r#"{"user":"app","password":"hunter2","key":[1,2,3,4]}"#;
+//! let mut config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(&*config.password.read().unwrap(), "hunter2");
+//! assert_eq!(serde_json::to_string(&config).unwrap(), json);
+//! ```
+//!
+//! The secret types deliberately do not implement `Serialize`: a derive on
+//! a struct that happens to hold a secret would otherwise write it to a log
+//! or a response body. Serializing is opted into per field with
+//! [`expose`].
+//!
+//! What the deserializer does before calling the visitor is beyond this
+//! crate's reach. `serde_json`, for example, hands over strings without
+//! escapes borrowed from the input, but unescapes other strings into a
+//! scratch `Vec`, and sequence elements pass through the stack one byte at
+//! a time. Deserializers that own their strings, such as `toml` and
+//! `serde_yaml`, build a heap `String` for every value; the visitor wipes
+//! it after the copy, but not any copies made before. Keep the input itself in protected memory (for example, parse
+//! from a [`SecretBytes`] guard) for the strongest guarantee.
+
+use std::fmt;
+
+use ::serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use ::serde::{Serialize, Serializer};
+
+use crate::cell::Cell;
+use crate::ptr_ops::secure_zero;
+use crate::{MemoryError, Secret, SecretBytes, SecretString};
+
+/// Longest `SecretBytes` accepted as a sequence of byte values when the
+/// deserializer gives no length hint.
+pub const MAX_SEQ_LEN: usize = 4096;
+
+/// Serialize a secret field, for `#[serde(serialize_with = "...")]`.
+///
+/// `Secret<N>` and `SecretBytes` serialize as bytes, `SecretString` as a
+/// string. The page is readable only while the serializer copies it;
+/// where the bytes go from there is up to the serializer.
+pub fn expose<T, S>(secret: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Expose + ?Sized,
+    S: Serializer,
+{
+    secret.serialize_exposed(serializer)
+}
+
+/// Secret types that [`expose`] can serialize. Sealed.
+pub trait Expose: private::Sealed {
+    /// Serialize the secret contents with `serializer`.
+    fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl<const N: usize> Sealed for crate::Secret<N> {}
+    impl Sealed for crate::SecretBytes {}
+    impl Sealed for crate::SecretString {}
+}
+
+impl<const N: usize> Expose for Secret<N> {
+    fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.peek(|bytes| serializer.serialize_bytes(bytes))
+            .map_err(::serde::ser::Error::custom)?
+    }
+}
+
+impl Expose for SecretBytes {
+    fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.peek(|bytes| serializer.serialize_bytes(bytes))
+            .map_err(::serde::ser::Error::custom)?
+    }
+}
+
+impl Expose for SecretString {
+    fn serialize_exposed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.peek(|s| s.serialize(serializer))
+            .map_err(::serde::ser::Error::custom)?
+    }
+}
+
+/// Accepts a string, bytes, or a sequence of byte values of at most `N`
+/// bytes, zero-padded like `Secret::try_from(&str)`.
+impl<'de, const N: usize> Deserialize<'de> for Secret<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(SecretVisitor::<N>)
+    }
+}
+
+/// Accepts a non-empty string, bytes, or sequence of byte values; a
+/// sequence is limited to its length hint or [`MAX_SEQ_LEN`].
+impl<'de> Deserialize<'de> for SecretBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+/// Accepts a non-empty string, or bytes that are valid UTF-8.
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StringVisitor)
+    }
+}
+
+struct SecretVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for SecretVisitor<N> {
+    type Value = Secret<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a secret of at most {N} bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        if v.len() > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Secret::try_new_with(|page| {
+            page[..v.len()].copy_from_slice(v);
+            Ok(())
+        })
+        .map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, mut v: Vec<u8>) -> Result<Self::Value, E> {
+        let secret = self.visit_bytes(&v);
+        secure_zero(&mut v);
+        secret
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visit_byte_buf(v.into_bytes())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut failure = None;
+        Secret::try_new_with(|page| fill_from_seq(&mut seq, page, &mut failure).map(drop))
+            .map_err(|e| failure.unwrap_or_else(|| de::Error::custom(e)))
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = SecretBytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-empty secret byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        SecretBytes::try_new_with(v.len(), |page| {
+            page.copy_from_slice(v);
+            Ok(())
+        })
+        .map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, mut v: Vec<u8>) -> Result<Self::Value, E> {
+        let secret = self.visit_bytes(&v);
+        secure_zero(&mut v);
+        secret
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visit_byte_buf(v.into_bytes())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // The hint comes from the input in formats like bincode: cap it
+        // before it sizes a locked page.
+        let max = seq.size_hint().unwrap_or(MAX_SEQ_LEN).clamp(1, MAX_SEQ_LEN);
+        let mut failure = None;
+        let mut len = 0;
+        let staging = Cell::<[u8]>::try_new_slice_with(max, |page| {
+            len = fill_from_seq(&mut seq, page, &mut failure)?;
+            Ok(())
+        });
+        let to_de = |e| failure.unwrap_or_else(|| de::Error::custom(e));
+        let mut staging = staging.map_err(to_de)?;
+        staging.read_only().map_err(de::Error::custom)?;
+        SecretBytes::try_new_with(len, |page| {
+            page.copy_from_slice(&staging[..len]);
+            Ok(())
+        })
+        .map_err(de::Error::custom)
+    }
+}
+
+struct StringVisitor;
+
+impl<'de> Visitor<'de> for StringVisitor {
+    type Value = SecretString;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-empty secret string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        SecretString::try_new_with(v.len(), |page| {
+            page.copy_from_slice(v);
+            Ok(())
+        })
+        .map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, mut v: Vec<u8>) -> Result<Self::Value, E> {
+        let secret = self.visit_bytes(&v);
+        secure_zero(&mut v);
+        secret
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visit_byte_buf(v.into_bytes())
+    }
+}
+
+/// Read byte elements of `seq` into `page`, returning how many were read.
+///
+/// A deserializer error, or a sequence longer than `page`, is stored in
+/// `failure` and reported to the page constructor as a placeholder, so the
+/// constructor wipes the page before the real error is returned.
+fn fill_from_seq<'de, A: SeqAccess<'de>>(
+    seq: &mut A,
+    page: &mut [u8],
+    failure: &mut Option<A::Error>,
+) -> Result<usize, MemoryError> {
+    let placeholder = || {
+        MemoryError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid byte sequence",
+        ))
+    };
+    let mut len = 0;
+    loop {
+        match seq.next_element::<u8>() {
+            Ok(Some(byte)) if len < page.len() => {
+                page[len] = byte;
+                len += 1;
+            }
+            Ok(Some(_)) => {
+                *failure = Some(de::Error::invalid_length(len + 1, &LongerThan(page.len())));
+                return Err(placeholder());
+            }
+            Ok(None) => return Ok(len),
+            Err(e) => {
+                *failure = Some(e);
+                return Err(placeholder());
+            }
+        }
+    }
+}
+
+/// `Expected` for an over-long sequence.
+struct LongerThan(usize);
+
+impl de::Expected for LongerThan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at most {} bytes", self.0)
+    }
+}
@@ -0,0 +1,380 @@
+//! SHA-256 (FIPS 180-4) and HMAC-SHA256 (RFC 2104), dependency-free.
+//!
+//! As with [`ChaCha20State`](crate::chacha::ChaCha20State), every value
+//! derived from the input — the chaining state, the message schedule and
+//! the pending block — is a field of the state struct rather than a
+//! function local, so callers can place the whole computation in a
+//! protected page.
+
+use crate::ptr_ops::ptr_fill_zero;
+
+/// Digest length in bytes.
+pub(crate) const DIGEST_LEN: usize = 32;
+
+/// Block length in bytes; also the HMAC key-padding length.
+const BLOCK_LEN: usize = 64;
+
+const H0: [u32; 8] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// An in-progress SHA-256 computation.
+pub(crate) struct Sha256 {
+    h: [u32; 8],
+    /// Message schedule and working variables of the current compression.
+    w: [u32; 64],
+    vars: [u32; 8],
+    block: [u8; BLOCK_LEN],
+    /// Bytes of `block` filled so far.
+    block_len: usize,
+    /// Total message length in bytes.
+    total: u64,
+}
+
+impl Sha256 {
+    pub(crate) const fn zeroed() -> Self {
+        Sha256 {
+            h: [0; 8],
+            w: [0; 64],
+            vars: [0; 8],
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            total: 0,
+        }
+    }
+
+    /// Start a new message.
+    pub(crate) fn reset(&mut self) {
+        self.resume(&H0, 0);
+    }
+
+    /// Continue from chaining value `h` after `total` bytes, which must be a
+    /// whole number of blocks.
+    fn resume(&mut self, h: &[u32; 8], total: u64) {
+        self.h = *h;
+        self.block_len = 0;
+        self.total = total;
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.total += data.len() as u64;
+        while !data.is_empty() {
+            let take = (BLOCK_LEN - self.block_len).min(data.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&data[..take]);
+            self.block_len += take;
+            data = &data[take..];
+            if self.block_len == BLOCK_LEN {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    /// Pad the message, write the digest to `out`, and leave the state
+    /// ready for [`Sha256::reset`].
+    pub(crate) fn finish(&mut self, out: &mut [u8; DIGEST_LEN]) {
+        let bits = self.total.wrapping_mul(8);
+        self.block[self.block_len] = 0x80;
+        self.block[self.block_len + 1..].fill(0);
+        if self.block_len >= BLOCK_LEN - 8 {
+            self.compress();
+            self.block.fill(0);
+        }
+        self.block[BLOCK_LEN - 8..].copy_from_slice(&bits.to_be_bytes());
+        self.compress();
+        for (chunk, word) in out.chunks_exact_mut(4).zip(&self.h) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    fn compress(&mut self) {
+        for (word, chunk) in self.w.iter_mut().zip(self.block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = self.w[i - 15].rotate_right(7)
+                ^ self.w[i - 15].rotate_right(18)
+                ^ (self.w[i - 15] >> 3);
+            let s1 = self.w[i - 2].rotate_right(17)
+                ^ self.w[i - 2].rotate_right(19)
+                ^ (self.w[i - 2] >> 10);
+            self.w[i] = self.w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(self.w[i - 7])
+                .wrapping_add(s1);
+        }
+        self.vars = self.h;
+        let v = &mut self.vars;
+        for (k, w) in K.iter().zip(&self.w) {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7]
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(*w);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+            v.copy_within(0..7, 1);
+            v[4] = v[4].wrapping_add(t1);
+            v[0] = t1.wrapping_add(t2);
+        }
+        for (h, v) in self.h.iter_mut().zip(&self.vars) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+}
+
+impl Drop for Sha256 {
+    fn drop(&mut self) {
+        ptr_fill_zero(self as *mut Self);
+    }
+}
+
+/// HMAC-SHA256 with the keyed inner and outer chaining values cached, so
+/// the many MACs under one key that PBKDF2 needs skip re-hashing the pads.
+pub(crate) struct HmacSha256 {
+    hash: Sha256,
+    inner_h: [u32; 8],
+    outer_h: [u32; 8],
+    pad: [u8; BLOCK_LEN],
+    digest: [u8; DIGEST_LEN],
+}
+
+impl HmacSha256 {
+    pub(crate) const fn zeroed() -> Self {
+        HmacSha256 {
+            hash: Sha256::zeroed(),
+            inner_h: [0; 8],
+            outer_h: [0; 8],
+            pad: [0; BLOCK_LEN],
+            digest: [0; DIGEST_LEN],
+        }
+    }
+
+    /// Load `key` and start the first message.
+    pub(crate) fn init(&mut self, key: &[u8]) {
+        self.pad.fill(0);
+        if key.len() > BLOCK_LEN {
+            self.hash.reset();
+            self.hash.update(key);
+            self.hash.finish(&mut self.digest);
+            self.pad[..DIGEST_LEN].copy_from_slice(&self.digest);
+        } else {
+            self.pad[..key.len()].copy_from_slice(key);
+        }
+        self.absorb_pad(0x36);
+        self.inner_h = self.hash.h;
+        self.absorb_pad(0x36 ^ 0x5c);
+        self.outer_h = self.hash.h;
+        self.pad.fill(0);
+        self.restart();
+    }
+
+    /// XOR `byte` into the pad and hash it as the first block of a new
+    /// message.
+    fn absorb_pad(&mut self, byte: u8) {
+        for p in self.pad.iter_mut() {
+            *p ^= byte;
+        }
+        self.hash.reset();
+        self.hash.update(&self.pad);
+    }
+
+    /// Start a new message under the loaded key.
+    pub(crate) fn restart(&mut self) {
+        self.hash.resume(&self.inner_h, BLOCK_LEN as u64);
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.hash.update(data);
+    }
+
+    /// Write the MAC of the current message to `out` and start a new one.
+    pub(crate) fn finish(&mut self, out: &mut [u8; DIGEST_LEN]) {
+        self.hash.finish(&mut self.digest);
+        self.hash.resume(&self.outer_h, BLOCK_LEN as u64);
+        self.hash.update(&self.digest);
+        self.hash.finish(out);
+        self.restart();
+    }
+}
+
+impl Drop for HmacSha256 {
+    fn drop(&mut self) {
+        ptr_fill_zero(self as *mut Self);
+    }
+}
+
+/// Test vectors from FIPS 180-4 examples and RFC 4231.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut state = Sha256::zeroed();
+        state.reset();
+        state.update(data);
+        let mut out = [0; 32];
+        state.finish(&mut out);
+        out
+    }
+
+    fn hmac(key: &[u8], data: &[u8]) -> [u8; 32] {
+        let mut state = HmacSha256::zeroed();
+        state.init(key);
+        state.update(data);
+        let mut out = [0; 32];
+        state.finish(&mut out);
+        out
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sha256_matches_fips_180_4() {
+        assert_eq!(
+            sha256(b"abc").to_vec(),
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            sha256(b"").to_vec(),
+            hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        // 56 bytes: the length no longer fits, forcing an extra padding block.
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_vec(),
+            hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        );
+    }
+
+    #[test]
+    fn split_updates_match_one_shot() {
+        let data: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let mut state = Sha256::zeroed();
+        state.reset();
+        for chunk in data.chunks(37) {
+            state.update(chunk);
+        }
+        let mut out = [0; 32];
+        state.finish(&mut out);
+        assert_eq!(out, sha256(&data));
+    }
+
+    #[test]
+    fn hmac_matches_rfc4231() {
+        // Test case 1.
+        assert_eq!(
+            hmac(&[0x0b; 20], b"Hi There").to_vec(),
+            hex("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7")
+        );
+        // Test case 2: key shorter than the output.
+        assert_eq!(
+            hmac(b"Jefe", b"what do ya want for nothing?").to_vec(),
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        // Test case 6: key longer than a block is hashed first.
+        assert_eq!(
+            hmac(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )
+            .to_vec(),
+            hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    #[test]
+    fn finish_restarts_under_the_same_key() {
+        let mut state = HmacSha256::zeroed();
+        state.init(b"Jefe");
+        let mut first = [0; 32];
+        state.update(b"message");
+        state.finish(&mut first);
+        let mut second = [0; 32];
+        state.update(b"message");
+        state.finish(&mut second);
+        assert_eq!(first, second);
+        assert_eq!(first, hmac(b"Jefe", b"message"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Shamir secret sharing over GF(256).
+//!
+//! [`split`] turns a `Secret<N>` into `n` shares, any `threshold` of which
+//! rebuild it with [`combine`]; fewer reveal nothing about it. Each byte of
+//! the secret is shared independently with its own random polynomial.
+//!
+//! A share is a `Secret<M>` with `M = N + 1`: byte 0 is the share's
+//! x-coordinate (1 to 255), the rest are the polynomial values. Stable Rust
+//! cannot spell `N + 1` in a signature, so `M` is a second parameter,
+//! checked at compile time:
+//!
+//! ```compile_fail
+//! # use memsafe::{Secret, shamir};
+//! let mut secret = Secret::<32>::random().unwrap();
+//! let shares: Vec<Secret<32>> = shamir::split(&mut secret, 2, 3).unwrap();
+//! ```
+//!
+//! The random coefficients live in a protected page, every share is
+//! computed directly in its own page, and [`combine`] reads the shares
+//! through guards and accumulates the result inside the new secret's page.
+//! Field arithmetic is branch-free and table-free, so its timing does not
+//! depend on secret bytes.
+//!
+//! ```
+//! use memsafe::{Secret, shamir};
+//!
+//! let mut secret = Secret::<32>::random().unwrap();
+//! let mut shares: Vec<Secret<33>> = shamir::split(&mut secret, 3, 5).unwrap();
+//!
+//! // Any three of the five shares are enough.
+//! let mut recovered: Secret<32> = shamir::combine(&mut shares[1..4]).unwrap();
+//! assert!(recovered.ct_eq(&mut secret).unwrap());
+//! ```
+
+use crate::{MemoryError, Secret, SecretBytes};
+
+/// Split `secret` into `shares` shares, any `threshold` of which recover
+/// it.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidInput` unless
+/// `2 <= threshold <= shares <= 255`. Entropy and protection failures are
+/// reported as for [`Secret::random`].
+pub fn split<const N: usize, const M: usize>(
+    secret: &mut Secret<N>,
+    threshold: usize,
+    shares: usize,
+) -> Result<Vec<Secret<M>>, MemoryError> {
+    const { assert!(M == N + 1, "a share of a Secret<N> is a Secret<N + 1>") };
+    if threshold < 2 || threshold > shares || shares > 255 {
+        return Err(invalid("need 2 <= threshold <= shares <= 255"));
+    }
+    // Coefficient `k` (for `x^k`, `k >= 1`) of byte `j`'s polynomial is
+    // `coeffs[(k - 1) * N + j]`; the constant term is the secret byte.
+    let mut coeffs = SecretBytes::random((threshold - 1) * N)?;
+    let coeffs = coeffs.read()?;
+    let secret = secret.read()?;
+
+    (1..=shares as u8)
+        .map(|x| {
+            Secret::<M>::new_with(|share| {
+                share[0] = x;
+                for (j, y) in share[1..].iter_mut().enumerate() {
+                    // Horner's rule, accumulating in the share's page.
+                    for k in (0..threshold - 1).rev() {
+                        *y = gf_mul(*y, x) ^ coeffs[k * N + j];
+                    }
+                    *y = gf_mul(*y, x) ^ secret[j];
+                }
+            })
+        })
+        .collect()
+}
+
+/// Recover a secret from shares produced by [`split`].
+///
+/// At least `threshold` distinct shares of the same split must be given;
+/// with fewer, the result is an unrelated value, and there is no way to
+/// tell from the shares alone.
+///
+/// # Errors
+///
+/// Returns a `MemoryError` of kind `InvalidInput` if `shares` is empty,
+/// or if two shares have the same x-coordinate or one has x-coordinate 0.
+pub fn combine<const N: usize, const M: usize>(
+    shares: &mut [Secret<M>],
+) -> Result<Secret<N>, MemoryError> {
+    const { assert!(M == N + 1, "a share of a Secret<N> is a Secret<N + 1>") };
+    if shares.is_empty() {
+        return Err(invalid("at least one share is required"));
+    }
+    let views = shares
+        .iter_mut()
+        .map(Secret::read)
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, a) in views.iter().enumerate() {
+        if a[0] == 0 || views[..i].iter().any(|b| b[0] == a[0]) {
+            return Err(invalid("shares must have distinct, non-zero x-coordinates"));
+        }
+    }
+
+    // Lagrange basis polynomials evaluated at 0. In GF(256) subtraction is
+    // XOR, so `(0 - x_m) / (x_i - x_m)` is `x_m / (x_i ^ x_m)`.
+    let mut basis = SecretBytes::new_with(views.len(), |basis| {
+        for (i, l) in basis.iter_mut().enumerate() {
+            let xi = views[i][0];
+            *l = 1;
+            for (m, other) in views.iter().enumerate() {
+                if m != i {
+                    let xm = other[0];
+                    *l = gf_mul(*l, gf_mul(xm, gf_inv(xi ^ xm)));
+                }
+            }
+        }
+    })?;
+    let basis = basis.read()?;
+
+    Secret::new_with(|out| {
+        for (j, s) in out.iter_mut().enumerate() {
+            for (view, l) in views.iter().zip(basis.iter()) {
+                *s ^= gf_mul(*l, view[1 + j]);
+            }
+        }
+    })
+}
+
+/// Multiplication in GF(2^8) modulo the AES polynomial
+/// `x^8 + x^4 + x^3 + x + 1`, without branches or table lookups.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        p ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    p
+}
+
+/// Multiplicative inverse as `a^254`; maps 0 to 0.
+fn gf_inv(a: u8) -> u8 {
+    // 254 = 0b1111_1110: square, then multiply in for each set bit.
+    let mut result = 1u8;
+    for bit in (0..8).rev() {
+        result = gf_mul(result, result);
+        if (254 >> bit) & 1 == 1 {
+            result = gf_mul(result, a);
+        }
+    }
+    result
+}
+
+fn invalid(msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+}
+
+/// The field arithmetic is private, so it is checked here.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_matches_fips_197() {
+        // FIPS 197 section 4.2: {57} * {83} = {c1}, {57} * {13} = {fe}.
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        assert_eq!(gf_mul(0x57, 1), 0x57);
+        assert_eq!(gf_mul(0x57, 0), 0);
+    }
+
+    #[test]
+    fn every_non_zero_element_has_an_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "a = {a:#04x}");
+        }
+        assert_eq!(gf_inv(0), 0);
+    }
+}
@@ -0,0 +1,210 @@
+//! Secrets stored as two XOR shares in separate pages.
+//!
+//! A [`SplitSecret`] never stores its plaintext at rest. It keeps two
+//! independently allocated, locked pages: a uniformly random *mask*, and the
+//! plaintext XOR the mask. Either page on its own is indistinguishable from
+//! random bytes, so a partial memory disclosure, or a single page that ends
+//! up in a crash report, reveals nothing.
+//!
+//! The plaintext is rebuilt into a fresh protected scratch page when a guard
+//! is taken, and that page is wiped and released when the guard drops. A
+//! write guard also draws a new mask, so shares captured before and after a
+//! write cannot be combined.
+//!
+//! ```
+//! use memsafe::split::SplitSecret;
+//!
+//! let mut secret = SplitSecret::<32>::new_with(|buf| {
+//!     buf[..10].copy_from_slice(b"my-api-key");
+//! }).unwrap();
+//!
+//! let view = secret.read().unwrap();
+//! assert_eq!(&view[..10], b"my-api-key");
+//! ```
+
+use std::ops::{Deref, DerefMut};
+
+use crate::MemoryError;
+use crate::cell::Cell;
+use crate::ffi::fill_random;
+
+/// A fixed-size secret held as two XOR shares in two protected pages.
+///
+/// Like [`Secret`](crate::Secret), it implements neither `Debug` nor
+/// `Display`.
+pub struct SplitSecret<const N: usize> {
+    mask: Cell<[u8; N]>,
+    masked: Cell<[u8; N]>,
+}
+
+// Both pages are owned exclusively by this value, like `MemSafe`'s.
+unsafe impl<const N: usize> Send for SplitSecret<N> {}
+
+impl<const N: usize> SplitSecret<N> {
+    /// Allocate an `N`-byte split secret and fill it in place.
+    ///
+    /// `init` writes the plaintext into a protected scratch page, which is
+    /// split into shares and then wiped and released. A panic in `init`
+    /// wipes the scratch page as with
+    /// [`Secret::new_with`](crate::Secret::new_with).
+    pub fn new_with<F>(init: F) -> Result<Self, MemoryError>
+    where
+        F: FnOnce(&mut [u8; N]),
+    {
+        let mut scratch = Cell::<[u8; N]>::new_with(init)?;
+
+        let mut mask = Cell::<[u8; N]>::try_new_with(|m| fill_random(m))?;
+
+        scratch.read_only()?;
+        mask.read_only()?;
+        let masked = Cell::<[u8; N]>::new_with(|m| xor(m, &scratch, &mask));
+        mask.low_priv()?;
+        // `scratch` drops at the end of this scope, wiping the plaintext.
+        Ok(SplitSecret {
+            mask,
+            masked: masked?,
+        })
+    }
+
+    /// Rebuild the plaintext into a scratch page and obtain temporary read
+    /// access to it. The scratch page is wiped and released when the guard
+    /// drops.
+    pub fn read(&mut self) -> Result<SplitRead<'_, N>, MemoryError> {
+        let mut scratch = self.combine()?;
+        scratch.read_only()?;
+        Ok(SplitRead {
+            scratch,
+            _secret: self,
+        })
+    }
+
+    /// Rebuild the plaintext into a scratch page and obtain temporary
+    /// read-write access to it. When the guard drops, the secret is split
+    /// again under a fresh mask and the scratch page is wiped and released.
+    pub fn write(&mut self) -> Result<SplitWrite<'_, N>, MemoryError> {
+        let mut scratch = self.combine()?;
+        scratch.read_write()?;
+        Ok(SplitWrite {
+            scratch,
+            secret: self,
+        })
+    }
+
+    /// A new sealed page holding `mask ^ masked`.
+    fn combine(&mut self) -> Result<Cell<[u8; N]>, MemoryError> {
+        self.mask.read_only()?;
+        self.masked.read_only()?;
+        let scratch = Cell::<[u8; N]>::new_with(|s| xor(s, &self.mask, &self.masked));
+        self.mask.low_priv()?;
+        self.masked.low_priv()?;
+        scratch
+    }
+
+    /// Re-split `plain` into the existing share pages under a fresh mask.
+    ///
+    /// `masked` is recomputed from whatever the mask page holds even if
+    /// drawing the new mask fails partway, so the shares always recombine
+    /// to `plain`.
+    fn reshare(&mut self, plain: &[u8; N]) -> Result<(), MemoryError> {
+        self.mask.read_write()?;
+        self.masked.read_write()?;
+        let filled = fill_random(&mut self.mask[..]);
+        xor(&mut self.masked, plain, &self.mask);
+        self.mask.low_priv()?;
+        self.masked.low_priv()?;
+        filled
+    }
+}
+
+fn xor<const N: usize>(out: &mut [u8; N], a: &[u8; N], b: &[u8; N]) {
+    for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+        *o = x ^ y;
+    }
+}
+
+/// Read guard of a [`SplitSecret`]. Owns the scratch page holding the
+/// rebuilt plaintext, which is wiped and released on drop.
+pub struct SplitRead<'a, const N: usize> {
+    scratch: Cell<[u8; N]>,
+    _secret: &'a mut SplitSecret<N>,
+}
+
+impl<const N: usize> Deref for SplitRead<'_, N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        self.scratch.deref()
+    }
+}
+
+/// Write guard of a [`SplitSecret`]. Re-splits the secret under a fresh
+/// mask on drop, then wipes and releases the scratch page.
+pub struct SplitWrite<'a, const N: usize> {
+    scratch: Cell<[u8; N]>,
+    secret: &'a mut SplitSecret<N>,
+}
+
+impl<const N: usize> Deref for SplitWrite<'_, N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        self.scratch.deref()
+    }
+}
+
+impl<const N: usize> DerefMut for SplitWrite<'_, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.scratch.deref_mut()
+    }
+}
+
+impl<const N: usize> Drop for SplitWrite<'_, N> {
+    fn drop(&mut self) {
+        self.secret.reshare(&self.scratch).unwrap();
+    }
+}
+
+/// The shares are private, so their at-rest contents are checked here
+/// rather than in `tests/`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shares<const N: usize>(secret: &mut SplitSecret<N>) -> ([u8; N], [u8; N]) {
+        secret.mask.read_only().unwrap();
+        secret.masked.read_only().unwrap();
+        let out = (*secret.mask, *secret.masked);
+        secret.mask.low_priv().unwrap();
+        secret.masked.low_priv().unwrap();
+        out
+    }
+
+    #[test]
+    fn neither_share_holds_the_plaintext() {
+        let plain = [0x42u8; 64];
+        let mut secret = SplitSecret::<64>::new_with(|b| *b = plain).unwrap();
+        let (mask, masked) = shares(&mut secret);
+        assert_ne!(mask, plain);
+        assert_ne!(masked, plain);
+        let rebuilt: Vec<u8> = mask.iter().zip(&masked).map(|(a, b)| a ^ b).collect();
+        assert_eq!(rebuilt, plain);
+    }
+
+    #[test]
+    fn write_draws_a_fresh_mask() {
+        let mut secret = SplitSecret::<32>::new_with(|b| b.fill(7)).unwrap();
+        let (before, _) = shares(&mut secret);
+        drop(secret.write().unwrap());
+        let (after, _) = shares(&mut secret);
+        assert_ne!(before, after);
+        assert!(secret.read().unwrap().iter().all(|&b| b == 7));
+    }
+
+    #[test]
+    fn read_leaves_shares_unchanged() {
+        let mut secret = SplitSecret::<32>::new_with(|b| b.fill(9)).unwrap();
+        let before = shares(&mut secret);
+        drop(secret.read().unwrap());
+        assert_eq!(shares(&mut secret), before);
+    }
+}
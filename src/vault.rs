@@ -0,0 +1,563 @@
+//! A privilege-separated key holder in a forked child process.
+//!
+//! [`Vault::spawn`] forks a small child that keeps keys in its own
+//! protected pages and answers requests over a socketpair, in the manner
+//! of OpenSSH's privilege separation. The parent holds only opaque
+//! [`KeyHandle`]s: keys are generated in the child, loaded there from a
+//! file, or imported once through a sealed memfd, and the parent asks the
+//! child to compute HMACs, compare candidates in constant time, or (with
+//! the `aead` feature) encrypt and decrypt. A memory disclosure in the
+//! parent cannot reveal a key it never maps.
+//!
+//! ```
+//! use memsafe::vault::Vault;
+//!
+//! let mut vault = Vault::spawn().unwrap();
+//! let key = vault.generate(32).unwrap();
+//! let mut mac = vault.hmac_sha256(key, b"session=42").unwrap();
+//! # let _ = mac.read().unwrap();
+//! ```
+//!
+//! The child marks itself non-dumpable (`PR_SET_DUMPABLE=0`), so other
+//! processes of the same user can neither `ptrace` it nor read its memory
+//! through `/proc`. It arms `PR_SET_PDEATHSIG`, which kills it when the
+//! *thread* that forked it exits, not only when the parent process does:
+//! call [`Vault::spawn`] from the main thread or another thread that lives
+//! as long as the vault. It closes every inherited descriptor except its
+//! end of the socket. Dropping the
+//! [`Vault`] closes the socket; the child then wipes its keys and exits,
+//! and the parent reaps it.
+//!
+//! The child is forked, not executed, so call [`Vault::spawn`] early,
+//! before the process starts threads that may hold locks the child would
+//! need. Linux only.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::command::SecretSource;
+use crate::file::SecretFile;
+use crate::ptr_ops::secure_zero;
+use crate::sha256::DIGEST_LEN;
+use crate::{MemoryError, Secret, SecretBytes, ct, kdf, memfd};
+
+/// Longest key the vault holds, in bytes.
+pub const MAX_KEY_LEN: usize = 16 * 1024;
+
+/// Longest request or reply body, in bytes; this bounds the messages,
+/// candidates and buffers passed to the child.
+pub const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+const OP_GENERATE: u8 = 1;
+const OP_IMPORT: u8 = 2;
+const OP_LOAD_FILE: u8 = 3;
+const OP_REMOVE: u8 = 4;
+const OP_HMAC: u8 = 5;
+const OP_VERIFY: u8 = 6;
+#[cfg(feature = "aead")]
+const OP_SEAL: u8 = 7;
+#[cfg(feature = "aead")]
+const OP_OPEN: u8 = 8;
+
+/// An opaque reference to a key held by a [`Vault`]. Handles are only
+/// meaningful to the vault that issued them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyHandle(u32);
+
+/// The parent's side of a key-holding child process.
+pub struct Vault {
+    socket: UnixStream,
+    pid: libc::pid_t,
+}
+
+impl Vault {
+    /// Fork the key-holding child.
+    ///
+    /// The child is killed when the calling thread exits, after which
+    /// every request fails; call this from the main thread or a thread
+    /// that outlives the vault.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` wrapping the error of `socketpair(2)` or
+    /// `fork(2)`.
+    pub fn spawn() -> Result<Vault, MemoryError> {
+        let (parent, child) = UnixStream::pair()?;
+        let parent_pid = unsafe { libc::getpid() };
+        match unsafe { libc::fork() } {
+            -1 => Err(std::io::Error::last_os_error().into()),
+            0 => {
+                drop(parent);
+                let served = panic::catch_unwind(AssertUnwindSafe(|| serve(child, parent_pid)));
+                unsafe { libc::_exit(if matches!(served, Ok(Ok(()))) { 0 } else { 1 }) }
+            }
+            pid => Ok(Vault {
+                socket: parent,
+                pid,
+            }),
+        }
+    }
+
+    /// The child's process id.
+    pub fn id(&self) -> u32 {
+        self.pid as u32
+    }
+
+    /// Generate a random `len`-byte key in the child.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MemoryError` of kind `InvalidInput` if `len` is zero or
+    /// larger than [`MAX_KEY_LEN`], or an error from the child.
+    pub fn generate(&mut self, len: usize) -> Result<KeyHandle, MemoryError> {
+        let len = u32::try_from(len).unwrap_or(u32::MAX);
+        self.request(OP_GENERATE, 0, &[&len.to_le_bytes()])?;
+        self.handle_reply()
+    }
+
+    /// Hand `secret` to the child through a sealed memfd (see
+    /// [`memfd::send`]). The caller's copy stays with the caller; drop it
+    /// once it has been imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns the child's error if it refuses the key. If the handoff
+    /// itself fails, the error of [`memfd::send`] is returned and the
+    /// vault is shut down, since the child may still be waiting for it.
+    pub fn import<S: SecretSource + ?Sized>(
+        &mut self,
+        secret: &mut S,
+    ) -> Result<KeyHandle, MemoryError> {
+        self.request(OP_IMPORT, 0, &[])?;
+        match memfd::send(&self.socket, secret) {
+            Ok(()) => self.handle_reply(),
+            // The child answered the handoff and now reports why.
+            Err(MemoryError::Io(e)) if e.kind() == ErrorKind::InvalidData => {
+                self.handle_reply().and(Err(MemoryError::from(e)))
+            }
+            // The child may still be waiting for the memfd: shut the vault
+            // down rather than leave the protocol out of step.
+            Err(e) => {
+                let _ = self.socket.shutdown(std::net::Shutdown::Both);
+                Err(e)
+            }
+        }
+    }
+
+    /// Have the child read the key file at `path`, of at most `max` bytes.
+    /// The file must not be readable by group or others (see
+    /// [`SecretFile::require_private`]), and is read with the child's
+    /// privileges.
+    ///
+    /// # Errors
+    ///
+    /// As for [`SecretFile::read_bytes`], reported by the child.
+    pub fn load_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        max: usize,
+    ) -> Result<KeyHandle, MemoryError> {
+        let max = u32::try_from(max.min(MAX_KEY_LEN)).unwrap_or(u32::MAX);
+        let path = path.as_ref().as_os_str().as_bytes();
+        self.request(OP_LOAD_FILE, 0, &[&max.to_le_bytes(), path])?;
+        self.handle_reply()
+    }
+
+    /// Wipe and forget the key behind `key`.
+    pub fn remove(&mut self, key: KeyHandle) -> Result<(), MemoryError> {
+        self.request(OP_REMOVE, key.0, &[])?;
+        self.reply_into(&mut [])
+    }
+
+    /// HMAC-SHA256 of `message` under `key`, computed in the child and
+    /// read straight into a new secret.
+    pub fn hmac_sha256(
+        &mut self,
+        key: KeyHandle,
+        message: &[u8],
+    ) -> Result<Secret<DIGEST_LEN>, MemoryError> {
+        self.request(OP_HMAC, key.0, &[message])?;
+        let len = self.reply_header()?;
+        Secret::try_new_with(|page| self.body_into(len, page))
+    }
+
+    /// Whether `candidate` equals the key, compared in constant time in
+    /// the child. Lengths are public.
+    pub fn verify(&mut self, key: KeyHandle, candidate: &[u8]) -> Result<bool, MemoryError> {
+        self.request(OP_VERIFY, key.0, &[candidate])?;
+        let mut equal = [0u8];
+        self.reply_into(&mut equal)?;
+        Ok(equal[0] == 1)
+    }
+
+    /// Encrypt `buffer` in place with ChaCha20-Poly1305 under the 32-byte
+    /// key `key`, as [`aead::encrypt_in_place`](crate::aead::encrypt_in_place)
+    /// does, and return the tag.
+    #[cfg(feature = "aead")]
+    pub fn seal(
+        &mut self,
+        key: KeyHandle,
+        nonce: &[u8; crate::aead::NONCE_LEN],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; crate::aead::TAG_LEN], MemoryError> {
+        let aad_len = u32::try_from(aad.len()).unwrap_or(u32::MAX);
+        let parts: [&[u8]; 4] = [nonce, &aad_len.to_le_bytes(), aad, buffer];
+        self.request(OP_SEAL, key.0, &parts)?;
+        let len = self.reply_header()?;
+        let mut tag = [0u8; crate::aead::TAG_LEN];
+        if len != buffer.len() + tag.len() {
+            return Err(protocol());
+        }
+        read_exact(&self.socket, buffer)?;
+        read_exact(&self.socket, &mut tag)?;
+        Ok(tag)
+    }
+
+    /// Verify `tag` and decrypt `buffer` in place under the 32-byte key
+    /// `key`, as [`aead::decrypt_in_place`](crate::aead::decrypt_in_place)
+    /// does.
+    #[cfg(feature = "aead")]
+    pub fn open(
+        &mut self,
+        key: KeyHandle,
+        nonce: &[u8; crate::aead::NONCE_LEN],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; crate::aead::TAG_LEN],
+    ) -> Result<(), MemoryError> {
+        let aad_len = u32::try_from(aad.len()).unwrap_or(u32::MAX);
+        let parts: [&[u8]; 5] = [nonce, tag, &aad_len.to_le_bytes(), aad, buffer];
+        self.request(OP_OPEN, key.0, &parts)?;
+        self.reply_into(buffer)
+    }
+
+    /// Send one request: op, handle, body length, then the body parts.
+    fn request(&mut self, op: u8, handle: u32, parts: &[&[u8]]) -> Result<(), MemoryError> {
+        let len: usize = parts.iter().map(|p| p.len()).sum();
+        if len > MAX_MESSAGE_LEN {
+            return Err(error(ErrorKind::InvalidInput, "request too large"));
+        }
+        let mut header = [0u8; 9];
+        header[0] = op;
+        header[1..5].copy_from_slice(&handle.to_le_bytes());
+        header[5..].copy_from_slice(&(len as u32).to_le_bytes());
+        (&self.socket).write_all(&header)?;
+        for part in parts {
+            (&self.socket).write_all(part)?;
+        }
+        Ok(())
+    }
+
+    /// Read a reply header and return the body length, or the child's
+    /// error.
+    fn reply_header(&mut self) -> Result<usize, MemoryError> {
+        let mut header = [0u8; 5];
+        read_exact(&self.socket, &mut header)?;
+        let len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+        if len > MAX_MESSAGE_LEN {
+            return Err(protocol());
+        }
+        if header[0] == 0 {
+            return Ok(len);
+        }
+        let mut msg = vec![0u8; len];
+        read_exact(&self.socket, &mut msg)?;
+        Err(MemoryError::from(std::io::Error::new(
+            kind_from_code(header[0]),
+            String::from_utf8_lossy(&msg).into_owned(),
+        )))
+    }
+
+    /// Read a reply whose body is exactly `out.len()` bytes into `out`.
+    fn reply_into(&mut self, out: &mut [u8]) -> Result<(), MemoryError> {
+        let len = self.reply_header()?;
+        self.body_into(len, out)
+    }
+
+    fn body_into(&mut self, len: usize, out: &mut [u8]) -> Result<(), MemoryError> {
+        if len != out.len() {
+            return Err(protocol());
+        }
+        read_exact(&self.socket, out)
+    }
+
+    fn handle_reply(&mut self) -> Result<KeyHandle, MemoryError> {
+        let mut handle = [0u8; 4];
+        self.reply_into(&mut handle)?;
+        Ok(KeyHandle(u32::from_le_bytes(handle)))
+    }
+}
+
+impl Drop for Vault {
+    fn drop(&mut self) {
+        let _ = self.socket.shutdown(std::net::Shutdown::Both);
+        let mut status = 0;
+        unsafe { libc::waitpid(self.pid, &mut status, 0) };
+    }
+}
+
+/// The child's request loop. Returns when the parent closes the socket.
+fn serve(socket: UnixStream, parent_pid: libc::pid_t) -> Result<(), MemoryError> {
+    harden(socket.as_raw_fd(), parent_pid)?;
+    let mut keys: HashMap<u32, SecretBytes> = HashMap::new();
+    let mut next = 1u32;
+    loop {
+        let mut header = [0u8; 9];
+        match (&socket).read(&mut header[..1]) {
+            Ok(0) => return Ok(()),
+            Ok(_) => read_exact(&socket, &mut header[1..])?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+        let handle = u32::from_le_bytes(header[1..5].try_into().unwrap());
+        let len = u32::from_le_bytes(header[5..].try_into().unwrap()) as usize;
+        if len > MAX_MESSAGE_LEN {
+            return Err(protocol());
+        }
+        let mut body = vec![0u8; len];
+        read_exact(&socket, &mut body)?;
+
+        let request = Request {
+            socket: &socket,
+            keys: &mut keys,
+            next: &mut next,
+        };
+        let sent = match request.handle(header[0], handle, &mut body) {
+            Ok(reply) => reply.send(&socket, &body),
+            Err(err) => {
+                let (code, msg) = match &err {
+                    MemoryError::Io(e) => (code_from_kind(e.kind()), e.to_string()),
+                    other => (code_from_kind(ErrorKind::Other), other.to_string()),
+                };
+                send_reply(&socket, code, &[msg.as_bytes()])
+            }
+        };
+        // Candidates, plaintext and replies staged in place: none of it
+        // outlives the request.
+        secure_zero(&mut body);
+        sent?;
+    }
+}
+
+/// Child-side setup: no core dumps or `ptrace`, die with the forking
+/// thread (`PR_SET_PDEATHSIG` tracks the thread, not the process), and
+/// keep no descriptor but the socket (and stdio).
+fn harden(socket: RawFd, parent_pid: libc::pid_t) -> Result<(), MemoryError> {
+    unsafe {
+        if libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) != 0
+            || libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL, 0, 0, 0) != 0
+        {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // The parent may have died before PDEATHSIG was armed.
+        if libc::getppid() != parent_pid {
+            return Err(error(ErrorKind::BrokenPipe, "parent exited"));
+        }
+        // Best effort: `close_range` needs Linux 5.9.
+        if socket > 3 {
+            libc::syscall(libc::SYS_close_range, 3, socket - 1, 0);
+        }
+        libc::syscall(libc::SYS_close_range, socket + 1, u32::MAX, 0);
+    }
+    Ok(())
+}
+
+struct Request<'a> {
+    socket: &'a UnixStream,
+    keys: &'a mut HashMap<u32, SecretBytes>,
+    next: &'a mut u32,
+}
+
+/// A successful reply from the child.
+enum Reply {
+    Empty,
+    Handle(u32),
+    Flag(bool),
+    Mac(Secret<DIGEST_LEN>),
+    /// The request body from `start` on, transformed in place, then the
+    /// tag if one was computed.
+    #[cfg(feature = "aead")]
+    Data {
+        start: usize,
+        tag: Option<[u8; crate::aead::TAG_LEN]>,
+    },
+}
+
+impl<'a> Request<'a> {
+    fn handle(self, op: u8, handle: u32, body: &mut [u8]) -> Result<Reply, MemoryError> {
+        match op {
+            OP_GENERATE => {
+                let len = u32::from_le_bytes(body.try_into().map_err(|_| protocol())?);
+                let len = len as usize;
+                if len == 0 || len > MAX_KEY_LEN {
+                    return Err(error(ErrorKind::InvalidInput, "key length out of range"));
+                }
+                self.insert(SecretBytes::random(len)?)
+            }
+            OP_IMPORT => {
+                let key = memfd::recv_bytes(self.socket, MAX_KEY_LEN)?;
+                self.insert(key)
+            }
+            OP_LOAD_FILE => {
+                if body.len() < 4 {
+                    return Err(protocol());
+                }
+                let max = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+                let path = OsStr::from_bytes(&body[4..]);
+                let key = SecretFile::new(path)
+                    .require_private()
+                    .read_bytes(max.min(MAX_KEY_LEN))?;
+                self.insert(key)
+            }
+            OP_REMOVE => {
+                self.keys.remove(&handle).ok_or_else(unknown_handle)?;
+                Ok(Reply::Empty)
+            }
+            OP_HMAC => {
+                let key = self.key(handle)?;
+                Ok(Reply::Mac(kdf::hmac_sha256(&key.read()?, body)?))
+            }
+            OP_VERIFY => {
+                let key = self.key(handle)?;
+                Ok(Reply::Flag(ct::eq(&key.read()?, body)))
+            }
+            #[cfg(feature = "aead")]
+            OP_SEAL | OP_OPEN => self.aead(op, handle, body),
+            _ => Err(protocol()),
+        }
+    }
+
+    fn insert(self, key: SecretBytes) -> Result<Reply, MemoryError> {
+        let handle = *self.next;
+        *self.next = self.next.checked_add(1).ok_or_else(protocol)?;
+        self.keys.insert(handle, key);
+        Ok(Reply::Handle(handle))
+    }
+
+    fn key(self, handle: u32) -> Result<&'a mut SecretBytes, MemoryError> {
+        self.keys.get_mut(&handle).ok_or_else(unknown_handle)
+    }
+
+    /// `OP_SEAL` body: nonce, AAD length, AAD, plaintext.
+    /// `OP_OPEN` body: nonce, tag, AAD length, AAD, ciphertext.
+    #[cfg(feature = "aead")]
+    fn aead(self, op: u8, handle: u32, body: &mut [u8]) -> Result<Reply, MemoryError> {
+        use crate::aead::{self, KEY_LEN, NONCE_LEN, TAG_LEN};
+
+        let total = body.len();
+        let stored = self.keys.get_mut(&handle).ok_or_else(unknown_handle)?;
+        if stored.len() != KEY_LEN {
+            return Err(error(ErrorKind::InvalidInput, "AEAD keys are 32 bytes"));
+        }
+        let mut key = Secret::<KEY_LEN>::try_new_with(|page| {
+            page.copy_from_slice(&stored.read()?);
+            Ok(())
+        })?;
+
+        let (nonce, rest) = body.split_at_mut_checked(NONCE_LEN).ok_or_else(protocol)?;
+        let nonce: &[u8; NONCE_LEN] = (&*nonce).try_into().unwrap();
+        let (tag, rest) = if op == OP_OPEN {
+            let (tag, rest) = rest.split_at_mut_checked(TAG_LEN).ok_or_else(protocol)?;
+            (Some(<[u8; TAG_LEN]>::try_from(&*tag).unwrap()), rest)
+        } else {
+            (None, rest)
+        };
+        let (aad_len, rest) = rest.split_at_mut_checked(4).ok_or_else(protocol)?;
+        let aad_len = u32::from_le_bytes((&*aad_len).try_into().unwrap()) as usize;
+        let (aad, data) = rest.split_at_mut_checked(aad_len).ok_or_else(protocol)?;
+
+        let start = total - data.len();
+        match tag {
+            Some(tag) => {
+                aead::decrypt_in_place(&mut key, nonce, aad, data, &tag)?;
+                Ok(Reply::Data { start, tag: None })
+            }
+            None => {
+                let tag = aead::encrypt_in_place(&mut key, nonce, aad, data)?;
+                Ok(Reply::Data {
+                    start,
+                    tag: Some(tag),
+                })
+            }
+        }
+    }
+}
+
+impl Reply {
+    /// Send the reply; `Data` replies are read from `body`.
+    #[cfg_attr(not(feature = "aead"), allow(unused_variables))]
+    fn send(self, socket: &UnixStream, body: &[u8]) -> Result<(), MemoryError> {
+        match self {
+            Reply::Empty => send_reply(socket, 0, &[]),
+            Reply::Handle(handle) => send_reply(socket, 0, &[&handle.to_le_bytes()]),
+            Reply::Flag(flag) => send_reply(socket, 0, &[&[flag as u8]]),
+            Reply::Mac(mut mac) => send_reply(socket, 0, &[&mac.read()?[..]]),
+            #[cfg(feature = "aead")]
+            Reply::Data { start, tag } => match tag {
+                Some(tag) => send_reply(socket, 0, &[&body[start..], &tag]),
+                None => send_reply(socket, 0, &[&body[start..]]),
+            },
+        }
+    }
+}
+
+/// Send one reply: status, body length, then the body parts.
+pub(crate) fn send_reply(
+    socket: &UnixStream,
+    status: u8,
+    parts: &[&[u8]],
+) -> Result<(), MemoryError> {
+    let len: usize = parts.iter().map(|p| p.len()).sum();
+    let mut header = [0u8; 5];
+    header[0] = status;
+    header[1..].copy_from_slice(&(len as u32).to_le_bytes());
+    let mut socket = socket;
+    socket.write_all(&header)?;
+    for part in parts {
+        socket.write_all(part)?;
+    }
+    Ok(())
+}
+
+fn read_exact(mut socket: &UnixStream, buf: &mut [u8]) -> Result<(), MemoryError> {
+    socket.read_exact(buf).map_err(MemoryError::from)
+}
+
+/// Error kinds carried across the socket; anything else becomes `Other`.
+const KINDS: [ErrorKind; 6] = [
+    ErrorKind::Other,
+    ErrorKind::InvalidInput,
+    ErrorKind::InvalidData,
+    ErrorKind::NotFound,
+    ErrorKind::PermissionDenied,
+    ErrorKind::UnexpectedEof,
+];
+
+pub(crate) fn code_from_kind(kind: ErrorKind) -> u8 {
+    KINDS.iter().position(|&k| k == kind).unwrap_or(0) as u8 + 1
+}
+
+pub(crate) fn kind_from_code(code: u8) -> ErrorKind {
+    KINDS
+        .get(code as usize - 1)
+        .copied()
+        .unwrap_or(ErrorKind::Other)
+}
+
+fn unknown_handle() -> MemoryError {
+    error(ErrorKind::NotFound, "unknown key handle")
+}
+
+fn protocol() -> MemoryError {
+    error(ErrorKind::InvalidData, "vault protocol violation")
+}
+
+fn error(kind: ErrorKind, msg: &'static str) -> MemoryError {
+    MemoryError::from(std::io::Error::new(kind, msg))
+}
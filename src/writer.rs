@@ -0,0 +1,65 @@
+//! Writing secrets from protected pages to sinks, without staging copies.
+
+use std::io::{ErrorKind, Write};
+
+use crate::MemoryError;
+
+/// Write all of `bytes` to `writer` and flush it.
+///
+/// Partial writes and `Interrupted` errors are retried; a write that
+/// accepts nothing is reported as `WriteZero`.
+pub(crate) fn write_all_to<W: Write + ?Sized>(
+    writer: &mut W,
+    mut bytes: &[u8],
+) -> Result<(), MemoryError> {
+    while !bytes.is_empty() {
+        match writer.write(bytes) {
+            Ok(0) => return Err(write_zero()),
+            Ok(n) => bytes = &bytes[n..],
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write all of `bytes` to `fd` with `write(2)`.
+///
+/// Partial writes and `EINTR` are retried. On a non-blocking descriptor,
+/// `EAGAIN` waits in `poll(2)` until the descriptor is writable again.
+#[cfg(unix)]
+pub(crate) fn write_all_to_fd(fd: std::os::fd::RawFd, mut bytes: &[u8]) -> Result<(), MemoryError> {
+    while !bytes.is_empty() {
+        let ret = unsafe { libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+        if ret > 0 {
+            bytes = &bytes[ret as usize..];
+            continue;
+        }
+        if ret == 0 {
+            return Err(write_zero());
+        }
+        let err = std::io::Error::last_os_error();
+        match err.kind() {
+            ErrorKind::Interrupted => {}
+            ErrorKind::WouldBlock => {
+                let mut poll = libc::pollfd {
+                    fd,
+                    events: libc::POLLOUT,
+                    revents: 0,
+                };
+                // Errors surface on the next write.
+                unsafe { libc::poll(&mut poll, 1, -1) };
+            }
+            _ => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+fn write_zero() -> MemoryError {
+    MemoryError::from(std::io::Error::new(
+        ErrorKind::WriteZero,
+        "sink accepted no bytes",
+    ))
+}
@@ -0,0 +1,88 @@
+//! ChaCha20-Poly1305 with a key held in a `Secret`.
+#![cfg(feature = "aead")]
+
+use memsafe::{Secret, aead};
+
+const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+one tip for the future, sunscreen would be it.";
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Key, nonce and associated data from RFC 8439 section 2.8.2.
+fn rfc_inputs() -> (Secret<32>, [u8; 12], Vec<u8>) {
+    let key = Secret::<32>::new_with(|k| {
+        for (i, b) in k.iter_mut().enumerate() {
+            *b = 0x80 + i as u8;
+        }
+    })
+    .unwrap();
+    let nonce = [7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+    (key, nonce, hex("50515253c0c1c2c3c4c5c6c7"))
+}
+
+#[test]
+fn encryption_matches_rfc8439() {
+    let (mut key, nonce, aad) = rfc_inputs();
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = aead::encrypt_in_place(&mut key, &nonce, &aad, &mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        hex(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116"
+        )
+    );
+    assert_eq!(tag.to_vec(), hex("1ae10b594f09e26a7e902ecbd0600691"));
+}
+
+#[test]
+fn empty_message_still_gets_a_tag() {
+    let (mut key, nonce, _) = rfc_inputs();
+    let tag = aead::encrypt_in_place(&mut key, &nonce, b"", &mut []).unwrap();
+    assert_eq!(tag.to_vec(), hex("a0784d7a4716f3feb4f64e7f4b39bf04"));
+    aead::decrypt_in_place(&mut key, &nonce, b"", &mut [], &tag).unwrap();
+}
+
+#[test]
+fn round_trip_in_place_and_into_secret() {
+    let (mut key, nonce, aad) = rfc_inputs();
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = aead::encrypt_in_place(&mut key, &nonce, &aad, &mut buffer).unwrap();
+
+    let mut plain = aead::decrypt_to_secret(&mut key, &nonce, &aad, &buffer, &tag).unwrap();
+    assert_eq!(&plain.read().unwrap()[..], PLAINTEXT);
+
+    aead::decrypt_in_place(&mut key, &nonce, &aad, &mut buffer, &tag).unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn tampering_is_rejected_and_leaves_ciphertext_untouched() {
+    let (mut key, nonce, aad) = rfc_inputs();
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = aead::encrypt_in_place(&mut key, &nonce, &aad, &mut buffer).unwrap();
+    let ciphertext = buffer.clone();
+
+    let mut flipped = buffer.clone();
+    flipped[3] ^= 1;
+    let mut bad_tag = tag;
+    bad_tag[0] ^= 1;
+    for (data, aad, tag) in [
+        (&mut flipped, &aad[..], &tag),
+        (&mut buffer.clone(), &b"other"[..], &tag),
+        (&mut buffer, &aad[..], &bad_tag),
+    ] {
+        let before = data.clone();
+        let err = aead::decrypt_in_place(&mut key, &nonce, aad, data, tag).unwrap_err();
+        assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(*data, before);
+    }
+    assert!(aead::decrypt_to_secret(&mut key, &nonce, &aad, &ciphertext, &bad_tag).is_err());
+}
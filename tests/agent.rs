@@ -0,0 +1,268 @@
+//! `agent`: the secret agent, its policy checks and its client.
+#![cfg(target_os = "linux")]
+
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::ops::Deref;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use memsafe::agent::{Agent, Client, PeerCred, Policy, UNLOCK_DELAY};
+use memsafe::{Secret, SecretBytes, SecretString};
+
+/// A fresh directory for one test's socket, removed with its contents on
+/// drop.
+struct SocketDir(PathBuf);
+
+impl SocketDir {
+    fn new() -> SocketDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "memsafe-agent-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        SocketDir(dir)
+    }
+
+    fn socket(&self) -> PathBuf {
+        self.0.join("agent.sock")
+    }
+}
+
+impl Drop for SocketDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A client of an agent served on a background thread. The thread runs
+/// for the rest of the test run; the socket goes with this value.
+struct Served {
+    client: Client,
+    _dir: SocketDir,
+}
+
+impl Deref for Served {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+fn start(policy: Policy) -> Served {
+    let dir = SocketDir::new();
+    let mut agent = Agent::bind(dir.socket(), policy).unwrap();
+    let client = Client::new(agent.path());
+    thread::spawn(move || agent.serve());
+    Served { client, _dir: dir }
+}
+
+#[test]
+fn secrets_round_trip_into_protected_memory() {
+    let agent = start(Policy::same_user());
+    let mut token = SecretString::try_from("ghp_token").unwrap();
+    agent.add("github", &mut token, None).unwrap();
+    let mut key = Secret::<32>::new_with(|k| k.fill(0x42)).unwrap();
+    agent.add("key", &mut key, None).unwrap();
+
+    let mut fetched: Secret<16> = agent.get("github").unwrap();
+    assert_eq!(&*fetched.read().unwrap(), b"ghp_token\0\0\0\0\0\0\0");
+    let mut fetched = agent.get_bytes("key", 64).unwrap();
+    assert_eq!(&*fetched.read().unwrap(), &[0x42; 32]);
+
+    let err = agent.get::<8>("github").err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    let err = agent.get_bytes("missing", 64).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+
+    // Adding again replaces.
+    let mut other = SecretBytes::new_with(3, |b| b.copy_from_slice(b"new")).unwrap();
+    agent.add("github", &mut other, None).unwrap();
+    let mut fetched = agent.get_bytes("github", 64).unwrap();
+    assert_eq!(&*fetched.read().unwrap(), b"new");
+
+    agent.delete("github").unwrap();
+    assert_eq!(
+        agent.delete("github").err().unwrap().inner().kind(),
+        ErrorKind::NotFound
+    );
+    assert!(agent.get::<32>("key").is_ok());
+}
+
+#[test]
+fn secrets_expire_after_their_ttl() {
+    let agent = start(Policy::same_user());
+    let mut short = SecretString::try_from("short").unwrap();
+    agent
+        .add("short", &mut short, Some(Duration::from_millis(100)))
+        .unwrap();
+    let mut long = SecretString::try_from("long").unwrap();
+    agent.add("long", &mut long, None).unwrap();
+    assert!(agent.get::<8>("short").is_ok());
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(
+        agent.get::<8>("short").err().unwrap().inner().kind(),
+        ErrorKind::NotFound
+    );
+    assert!(agent.get::<8>("long").is_ok());
+}
+
+#[test]
+fn locked_agent_refuses_until_unlocked_and_can_still_be_wiped() {
+    let agent = start(Policy::same_user());
+    let mut secret = SecretString::try_from("s3cret").unwrap();
+    agent.add("a", &mut secret, None).unwrap();
+
+    let mut passphrase = SecretString::try_from("correct horse").unwrap();
+    agent.lock(&mut passphrase).unwrap();
+    let err = agent.get::<8>("a").err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+    let err = agent.add("b", &mut secret, None).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+    let err = agent.lock(&mut passphrase).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+
+    let mut wrong = SecretString::try_from("wrong horse").unwrap();
+    let err = agent.unlock(&mut wrong).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+
+    agent.wipe_all().unwrap();
+    agent.unlock(&mut passphrase).unwrap();
+    let err = agent.get::<8>("a").err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+    let err = agent.unlock(&mut passphrase).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn wrong_passphrases_are_refused_ever_more_slowly() {
+    let agent = start(Policy::same_user());
+    let mut passphrase = SecretString::try_from("correct horse").unwrap();
+    agent.lock(&mut passphrase).unwrap();
+
+    let mut wrong = SecretString::try_from("wrong horse").unwrap();
+    let mut delays = Vec::new();
+    for _ in 0..3 {
+        let start = Instant::now();
+        let err = agent.unlock(&mut wrong).err().unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+        delays.push(start.elapsed());
+    }
+    for (failures, delay) in (1..).zip(&delays) {
+        assert!(*delay >= UNLOCK_DELAY * failures, "{delays:?}");
+    }
+
+    // A correct passphrase resets the count.
+    agent.unlock(&mut passphrase).unwrap();
+    agent.lock(&mut passphrase).unwrap();
+    let start = Instant::now();
+    assert!(agent.unlock(&mut wrong).is_err());
+    assert!(start.elapsed() < UNLOCK_DELAY * 2);
+}
+
+#[test]
+fn peers_outside_the_policy_are_refused() {
+    let (a, _b) = UnixStream::pair().unwrap();
+    let me = PeerCred::of(&a).unwrap();
+    assert_eq!(me.pid, std::process::id());
+    assert_eq!(me.uid, unsafe { libc::geteuid() });
+
+    let mut big = SecretBytes::random(memsafe::agent::MAX_SECRET_LEN).unwrap();
+    let nobody = start(Policy::deny_all());
+    let err = nobody.add("k", &mut big, None).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+    let err = nobody.get::<8>("k").err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+
+    let other_pid = start(Policy::same_user().allow_pid(me.pid + 1));
+    let err = other_pid.wipe_all().err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::PermissionDenied);
+
+    let this_pid = start(Policy::same_user().allow_pid(me.pid));
+    this_pid.add("k", &mut big, None).unwrap();
+    assert_eq!(
+        this_pid.get_bytes("k", usize::MAX).unwrap().len(),
+        big.len()
+    );
+}
+
+#[test]
+fn bad_requests_are_rejected() {
+    let agent = start(Policy::same_user());
+    let mut secret = SecretString::try_from("x").unwrap();
+    let long_name = "n".repeat(memsafe::agent::MAX_NAME_LEN + 1);
+    let err = agent.add(&long_name, &mut secret, None).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    let err = agent.add("", &mut secret, None).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    let mut huge = SecretBytes::random(memsafe::agent::MAX_SECRET_LEN + 1).unwrap();
+    let err = agent.add("huge", &mut huge, None).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    assert!(agent.add("ok", &mut secret, None).is_ok());
+}
+
+#[test]
+fn live_sockets_are_kept_and_stale_ones_replaced() {
+    let dir = SocketDir::new();
+    let path = dir.socket();
+    let first = Agent::bind(&path, Policy::same_user()).unwrap();
+    let err = Agent::bind(&path, Policy::same_user()).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::AddrInUse);
+    drop(first);
+    assert!(!path.exists());
+
+    // A listener that went away without removing its socket.
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+    let agent = Agent::bind(&path, Policy::same_user()).unwrap();
+    let mode = std::os::unix::fs::PermissionsExt::mode(&path.metadata().unwrap().permissions());
+    assert_eq!(mode & 0o777, 0o600);
+    drop(agent);
+}
+
+#[test]
+fn binary_prints_its_socket_and_serves() {
+    let dir = SocketDir::new();
+    let path = dir.socket();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_memsafe-agent"))
+        .arg("--socket")
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    assert_eq!(
+        line,
+        format!(
+            "MEMSAFE_AUTH_SOCK={}; export MEMSAFE_AUTH_SOCK;\n",
+            path.display()
+        )
+    );
+
+    let agent = Client::new(&path);
+    let mut secret = SecretString::try_from("from the binary").unwrap();
+    agent.add("s", &mut secret, None).unwrap();
+    let mut fetched = agent.get_bytes("s", 64).unwrap();
+    assert_eq!(&*fetched.read().unwrap(), b"from the binary");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_memsafe-agent"))
+        .arg("--bogus")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
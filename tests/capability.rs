@@ -0,0 +1,55 @@
+//! Tests for capability-gated secrets: a `CapSecret` opens only for the
+//! tokens minted with it.
+
+use memsafe::Secret;
+use memsafe::capability::CapSecret;
+
+#[test]
+fn matching_caps_open_the_secret() {
+    let (mut secret, read_cap, write_cap) =
+        CapSecret::<16>::new_with(|buf| buf[..4].copy_from_slice(b"init")).unwrap();
+    {
+        let mut w = secret.write(&write_cap).unwrap();
+        w[..7].copy_from_slice(b"updated");
+    }
+    let view = secret.read(&read_cap).unwrap();
+    assert_eq!(&view[..7], b"updated");
+}
+
+#[test]
+fn foreign_read_cap_is_rejected() {
+    let (mut a, _a_read, _a_write) = CapSecret::<16>::new_with(|b| b.fill(0xAA)).unwrap();
+    let (_b, b_read, _b_write) = CapSecret::<16>::new_with(|b| b.fill(0xBB)).unwrap();
+
+    let err = a.read(&b_read).err().expect("foreign cap must not open");
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn foreign_write_cap_is_rejected_and_leaves_secret_untouched() {
+    let (mut a, a_read, _a_write) = CapSecret::<16>::new_with(|b| b.fill(0xAA)).unwrap();
+    let (_b, _b_read, b_write) = CapSecret::<16>::new_with(|b| b.fill(0xBB)).unwrap();
+
+    let err = a.write(&b_write).err().expect("foreign cap must not open");
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(a.read(&a_read).unwrap().iter().all(|&b| b == 0xAA));
+}
+
+#[test]
+fn from_secret_keeps_contents() {
+    let plain = Secret::<8>::new_with(|b| b.copy_from_slice(b"contents")).unwrap();
+    let (mut secret, read_cap, _write_cap) = CapSecret::from_secret(plain);
+    assert_eq!(&secret.read(&read_cap).unwrap()[..], b"contents");
+}
+
+#[test]
+fn caps_can_be_handed_to_another_thread() {
+    let (mut secret, read_cap, _write_cap) =
+        CapSecret::<8>::new_with(|b| b.copy_from_slice(b"threaded")).unwrap();
+    let handle = std::thread::spawn(move || {
+        let ok = secret.read(&read_cap).unwrap()[..] == *b"threaded";
+        (secret, read_cap, ok)
+    });
+    let (_secret, _read_cap, ok) = handle.join().unwrap();
+    assert!(ok);
+}
@@ -0,0 +1,152 @@
+//! `SecretCommand` and `Secret::from_command`, driven through `sh -c`.
+#![cfg(unix)]
+
+use std::io::ErrorKind;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use memsafe::command::{SecretCommand, SecretCommandExt};
+use memsafe::error::MemoryError;
+use memsafe::{Secret, SecretBytes, SecretString};
+
+fn sh(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[test]
+fn captures_stdout_with_and_without_trimming() {
+    let mut secret = Secret::<16>::from_command(sh("echo hunter2")).unwrap();
+    assert_eq!(&secret.read().unwrap()[..8], b"hunter2\n");
+
+    let mut secret = SecretCommand::new(sh("printf 'hunter2\\r\\n'"))
+        .trim_newline()
+        .read_bytes(7)
+        .unwrap();
+    assert_eq!(&*secret.read().unwrap(), b"hunter2");
+
+    // Only one newline is trimmed.
+    let mut secret = SecretCommand::new(sh("printf 'a\\n\\n'"))
+        .trim_newline()
+        .read_bytes(8)
+        .unwrap();
+    assert_eq!(&*secret.read().unwrap(), b"a\n");
+}
+
+#[test]
+fn output_larger_than_the_page_is_captured_in_full() {
+    let mut secret = SecretCommand::new(sh("head -c 100000 /dev/zero | tr '\\0' x"))
+        .read_bytes(100_000)
+        .unwrap();
+    assert_eq!(secret.len(), 100_000);
+    assert!(secret.read().unwrap().iter().all(|&b| b == b'x'));
+}
+
+#[test]
+fn output_over_the_limit_is_rejected() {
+    let err = Secret::<4>::from_command(sh("printf 12345")).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+
+    // The child is killed rather than left blocked on a full pipe.
+    let err = SecretCommand::new(sh("yes")).read_bytes(16).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+
+    let err = SecretCommand::new(sh("printf x"))
+        .read_bytes(usize::MAX)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn failing_command_is_an_error() {
+    let err = Secret::<16>::from_command(sh("echo partial; exit 3"))
+        .err()
+        .unwrap();
+    let MemoryError::Io(err) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert!(err.to_string().contains('3'), "{err}");
+
+    let err = Secret::<16>::from_command(Command::new("/nonexistent/helper"))
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn timeout_kills_the_child() {
+    let start = Instant::now();
+    // Writes nothing and keeps stdout open.
+    let err = SecretCommand::new(sh("exec sleep 30"))
+        .timeout(Duration::from_millis(200))
+        .read_bytes(16)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::TimedOut);
+
+    // Closes stdout, then keeps running.
+    let err = SecretCommand::new(sh("echo early; exec sleep 30 >&-"))
+        .timeout(Duration::from_millis(200))
+        .read_bytes(16)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn secrets_are_fed_on_stdin_and_extra_descriptors() {
+    let mut user = SecretString::try_from("alice").unwrap();
+    let mut password = Secret::<7>::new_with(|b| b.copy_from_slice(b"hunter2")).unwrap();
+    let mut key = SecretBytes::new_with(3, |b| b.copy_from_slice(b"k3y")).unwrap();
+
+    let mut command = sh("cat; printf :; cat <&3; printf :; cat <&5");
+    command.stdout(Stdio::piped());
+    let child = command
+        .secret_stdin(&mut user)
+        .secret_fd(3, &mut password)
+        .secret_fd(5, &mut key)
+        .spawn()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"alice:hunter2:k3y");
+
+    // The mapping does not outlive the spawn it was set up for: reused, the
+    // command reads an empty stdin and finds descriptors 3 and 5 closed.
+    let output = command.output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(output.stdout, b"::");
+}
+
+#[test]
+fn invalid_targets_are_rejected() {
+    let mut secret = Secret::<4>::random().unwrap();
+    let mut other = Secret::<4>::random().unwrap();
+    let mut command = sh("true");
+    for fd in [1, 2, -1] {
+        let err = command.secret_fd(fd, &mut secret).spawn().err().unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::InvalidInput, "fd {fd}");
+    }
+    let err = command
+        .secret_fd(3, &mut secret)
+        .secret_fd(3, &mut other)
+        .spawn()
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn child_that_does_not_read_is_reaped_with_an_error() {
+    // Larger than any pipe buffer, so the write fails once the child exits.
+    let mut secret = SecretBytes::random(4 << 20).unwrap();
+    let err = sh("exit 0")
+        .secret_stdin(&mut secret)
+        .spawn()
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::BrokenPipe);
+}
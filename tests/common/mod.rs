@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+/// True when the tests run under a user-mode emulator (the cross/qemu CI
+/// targets). There /proc describes the emulator rather than the guest,
+/// fork+madvise semantics don't match a real kernel, and syscall overhead
+/// is not representative of any real deployment.
+pub fn emulated_kernel() -> bool {
+    std::env::vars().any(|(k, _)| {
+        k == "QEMU_LD_PREFIX"
+            || k == "CROSS_RUNNER"
+            || (k.starts_with("CARGO_TARGET_") && k.ends_with("_RUNNER"))
+    })
+}
+
+/// Read `len` bytes at `addr` through `/proc/self/mem`, which ignores
+/// `PROT_NONE`.
+#[cfg(target_os = "linux")]
+pub fn peek(addr: usize, len: usize) -> Vec<u8> {
+    use std::os::unix::fs::FileExt;
+    let mem = std::fs::File::open("/proc/self/mem").unwrap();
+    let mut out = vec![0u8; len];
+    mem.read_exact_at(&mut out, addr as u64).unwrap();
+    out
+}
+
+/// Write `bytes` at `addr` through `/proc/self/mem`, which ignores
+/// `PROT_NONE`.
+#[cfg(target_os = "linux")]
+pub fn poke(addr: usize, bytes: &[u8]) {
+    use std::os::unix::fs::FileExt;
+    let mem = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/proc/self/mem")
+        .unwrap();
+    mem.write_all_at(bytes, addr as u64).unwrap();
+}
@@ -0,0 +1,94 @@
+//! Tests for the constant-time operations on `Secret<N>`. These check the
+//! results; timing behavior is a property of the implementation (no
+//! data-dependent branches) that a unit test cannot measure reliably.
+
+use memsafe::Secret;
+
+fn secret<const N: usize>(bytes: &[u8]) -> Secret<N> {
+    Secret::new_with(|buf| buf[..bytes.len()].copy_from_slice(bytes)).unwrap()
+}
+
+#[test]
+fn ct_eq_between_secrets() {
+    let mut a = secret::<16>(b"same-bytes");
+    let mut b = secret::<16>(b"same-bytes");
+    let mut c = secret::<16>(b"same-bytez");
+    assert!(a.ct_eq(&mut b).unwrap());
+    assert!(!a.ct_eq(&mut c).unwrap());
+}
+
+#[test]
+fn ct_eq_detects_difference_in_last_byte() {
+    let mut a = Secret::<64>::new_with(|b| b.fill(1)).unwrap();
+    let mut b = Secret::<64>::new_with(|b| {
+        b.fill(1);
+        b[63] = 2;
+    })
+    .unwrap();
+    assert!(!a.ct_eq(&mut b).unwrap());
+}
+
+#[test]
+fn ct_eq_slice_requires_exact_length() {
+    let mut s = secret::<8>(b"password");
+    assert!(s.ct_eq_slice(b"password").unwrap());
+    assert!(!s.ct_eq_slice(b"passwore").unwrap());
+    assert!(!s.ct_eq_slice(b"passwor").unwrap());
+    assert!(!s.ct_eq_slice(b"password!").unwrap());
+}
+
+#[test]
+fn ct_eq_padded_compares_against_zero_padding() {
+    let mut s = secret::<64>(b"hunter2");
+    assert!(s.ct_eq_padded(b"hunter2").unwrap());
+    assert!(!s.ct_eq_padded(b"hunter").unwrap());
+    assert!(!s.ct_eq_padded(b"hunter22").unwrap());
+    assert!(!s.ct_eq_padded(b"").unwrap());
+    assert!(!s.ct_eq_padded(&[b'x'; 65]).unwrap());
+
+    let mut empty = Secret::<16>::new_with(|_| {}).unwrap();
+    assert!(empty.ct_eq_padded(b"").unwrap());
+}
+
+#[test]
+fn ct_assign_respects_choice() {
+    let mut dst = secret::<8>(b"original");
+    let mut src = secret::<8>(b"replaced");
+
+    dst.ct_assign(&mut src, false).unwrap();
+    assert_eq!(&dst.read().unwrap()[..], b"original");
+
+    dst.ct_assign(&mut src, true).unwrap();
+    assert_eq!(&dst.read().unwrap()[..], b"replaced");
+    assert_eq!(&src.read().unwrap()[..], b"replaced");
+}
+
+#[test]
+fn ct_swap_respects_choice() {
+    let mut a = secret::<4>(b"aaaa");
+    let mut b = secret::<4>(b"bbbb");
+
+    a.ct_swap(&mut b, false).unwrap();
+    assert_eq!(&a.read().unwrap()[..], b"aaaa");
+    assert_eq!(&b.read().unwrap()[..], b"bbbb");
+
+    a.ct_swap(&mut b, true).unwrap();
+    assert_eq!(&a.read().unwrap()[..], b"bbbb");
+    assert_eq!(&b.read().unwrap()[..], b"aaaa");
+}
+
+#[test]
+fn xor_assign_is_an_involution() {
+    let mut data = secret::<8>(b"payload!");
+    let mut pad = Secret::<8>::new_with(|b| {
+        for (i, x) in b.iter_mut().enumerate() {
+            *x = 0x30 + i as u8;
+        }
+    })
+    .unwrap();
+
+    data.xor_assign(&mut pad).unwrap();
+    assert_ne!(&data.read().unwrap()[..], b"payload!");
+    data.xor_assign(&mut pad).unwrap();
+    assert_eq!(&data.read().unwrap()[..], b"payload!");
+}
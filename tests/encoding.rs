@@ -0,0 +1,108 @@
+//! `encoding::{hex, base64}`: RFC 4648 vectors, round trips and rejects.
+
+use std::io::ErrorKind;
+
+use memsafe::encoding::base64::{self, Alphabet};
+use memsafe::encoding::hex;
+use memsafe::{Secret, SecretString};
+
+/// RFC 4648 section 10.
+const VECTORS: [(&str, &str, &str); 6] = [
+    ("f", "Zg==", "66"),
+    ("fo", "Zm8=", "666f"),
+    ("foo", "Zm9v", "666f6f"),
+    ("foob", "Zm9vYg==", "666f6f62"),
+    ("fooba", "Zm9vYmE=", "666f6f6261"),
+    ("foobar", "Zm9vYmFy", "666f6f626172"),
+];
+
+#[test]
+fn rfc_4648_vectors() {
+    for (plain, b64, hx) in VECTORS {
+        let mut encoded = base64::encode(Alphabet::Standard, plain.as_bytes()).unwrap();
+        assert_eq!(&*encoded.read().unwrap(), b64);
+        let mut encoded = base64::encode(Alphabet::UrlSafe, plain.as_bytes()).unwrap();
+        assert_eq!(&*encoded.read().unwrap(), b64.trim_end_matches('='));
+        let mut encoded = hex::encode(plain.as_bytes()).unwrap();
+        assert_eq!(&*encoded.read().unwrap(), hx);
+
+        for input in [b64, b64.trim_end_matches('=')] {
+            let mut decoded = base64::decode_bytes(Alphabet::Standard, input.as_bytes()).unwrap();
+            assert_eq!(&*decoded.read().unwrap(), plain.as_bytes(), "{input}");
+        }
+        let mut decoded = hex::decode_bytes(hx.as_bytes()).unwrap();
+        assert_eq!(&*decoded.read().unwrap(), plain.as_bytes());
+    }
+}
+
+#[test]
+fn every_byte_value_round_trips() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    for alphabet in [Alphabet::Standard, Alphabet::UrlSafe] {
+        let mut encoded = base64::encode(alphabet, &bytes).unwrap();
+        let mut decoded =
+            base64::decode_bytes(alphabet, encoded.read().unwrap().as_bytes()).unwrap();
+        assert_eq!(&*decoded.read().unwrap(), &bytes[..]);
+    }
+    let mut encoded = hex::encode(&bytes).unwrap();
+    let mut decoded = hex::decode_bytes(encoded.read().unwrap().as_bytes()).unwrap();
+    assert_eq!(&*decoded.read().unwrap(), &bytes[..]);
+}
+
+#[test]
+fn decodes_from_a_secret_into_a_fixed_size_secret() {
+    let mut encoded =
+        SecretString::try_from("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=").unwrap();
+    let mut key: Secret<32> =
+        base64::decode(Alphabet::Standard, encoded.read().unwrap().as_bytes()).unwrap();
+    let expected: Vec<u8> = (0..32).collect();
+    assert_eq!(&key.read().unwrap()[..], &expected[..]);
+
+    let mut key = hex::decode::<2>(b"C0fE").unwrap();
+    assert_eq!(*key.read().unwrap(), [0xc0, 0xfe]);
+}
+
+#[test]
+fn malformed_input_is_rejected() {
+    let cases: [(&[u8], ErrorKind); 7] = [
+        (b"Zm9v!A==", ErrorKind::InvalidData),
+        (b"Zm9v_w==", ErrorKind::InvalidData), // URL-safe character
+        (b"Zh==", ErrorKind::InvalidData),     // non-zero trailing bits
+        (b"Zm9vY", ErrorKind::InvalidInput),   // impossible length
+        (b"Zg=", ErrorKind::InvalidInput),     // short padding
+        (b"=", ErrorKind::InvalidInput),
+        (b"", ErrorKind::InvalidInput), // zero-length secret
+    ];
+    for (input, expected) in cases {
+        let err = base64::decode_bytes(Alphabet::Standard, input)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.inner().kind(),
+            expected,
+            "{:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+    let err = base64::decode_bytes(Alphabet::UrlSafe, b"Zm9v+w")
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+    let err = base64::decode::<4>(Alphabet::Standard, b"Zm9v")
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+
+    assert_eq!(
+        hex::decode_bytes(b"abc").err().unwrap().inner().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        hex::decode_bytes(b"0g").err().unwrap().inner().kind(),
+        ErrorKind::InvalidData
+    );
+    assert_eq!(
+        hex::decode::<2>(b"00").err().unwrap().inner().kind(),
+        ErrorKind::InvalidInput
+    );
+}
@@ -0,0 +1,121 @@
+//! Tests for `EncryptedSecret`: values round-trip through the guards, and
+//! while sealed the page holds ciphertext, not plaintext.
+
+mod common;
+
+use memsafe::encrypted::{EncryptedSecret, KEY_LEN, NONCE_LEN, SealCipher};
+
+#[test]
+fn new_with_round_trips_through_read() {
+    let mut secret = EncryptedSecret::<32>::new_with(|buf| {
+        buf[..10].copy_from_slice(b"my-api-key");
+    })
+    .unwrap();
+    let view = secret.read().unwrap();
+    assert_eq!(&view[..10], b"my-api-key");
+    assert!(view[10..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn writes_persist_across_reseals() {
+    let mut secret = EncryptedSecret::<16>::new_with(|_| {}).unwrap();
+    for round in 0u8..20 {
+        {
+            let mut w = secret.write().unwrap();
+            w.fill(round);
+            assert_eq!(w[15], round);
+        }
+        let r = secret.read().unwrap();
+        assert!(r.iter().all(|&b| b == round));
+    }
+}
+
+#[test]
+fn repeated_reads_are_stable() {
+    let mut secret = EncryptedSecret::<64>::new_with(|buf| buf.fill(0x5A)).unwrap();
+    for _ in 0..50 {
+        assert!(secret.read().unwrap().iter().all(|&b| b == 0x5A));
+    }
+}
+
+#[test]
+fn panic_in_init_propagates() {
+    let result = std::panic::catch_unwind(|| {
+        EncryptedSecret::<32>::new_with(|buf| {
+            buf[..3].copy_from_slice(b"abc");
+            panic!("simulated init failure");
+        })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn encrypted_secret_is_send_across_threads() {
+    let mut secret = EncryptedSecret::<16>::new_with(|b| b[..5].copy_from_slice(b"moved")).unwrap();
+    let handle = std::thread::spawn(move || {
+        assert_eq!(&secret.read().unwrap()[..5], b"moved");
+        secret
+    });
+    let mut secret = handle.join().unwrap();
+    assert_eq!(&secret.read().unwrap()[..5], b"moved");
+}
+
+/// A deliberately weak cipher, to show the cipher really is pluggable: it
+/// XORs with the first key byte and the nonce.
+struct XorCipher;
+
+impl SealCipher for XorCipher {
+    fn apply(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], data: &mut [u8]) {
+        for (i, b) in data.iter_mut().enumerate() {
+            *b ^= key[0] ^ nonce[i % NONCE_LEN] ^ 0xFF;
+        }
+    }
+}
+
+#[test]
+fn custom_cipher_round_trips() {
+    let mut secret =
+        EncryptedSecret::<24, XorCipher>::new_with(|b| b[..6].copy_from_slice(b"custom")).unwrap();
+    {
+        let mut w = secret.write().unwrap();
+        w[6..9].copy_from_slice(b"!!!");
+    }
+    assert_eq!(&secret.read().unwrap()[..9], b"custom!!!");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn sealed_page_holds_only_ciphertext() {
+    if common::emulated_kernel() {
+        eprintln!("skipping: /proc/self/mem under qemu describes the emulator, not the guest");
+        return;
+    }
+
+    const PLAINTEXT: &[u8; 32] = b"plaintext-must-never-be-at-rest!";
+
+    // Control: a plain `Secret` is readable through /proc/self/mem while
+    // sealed, which is exactly the gap encryption at rest closes.
+    let mut plain = memsafe::Secret::<32>::new_with(|b| b.copy_from_slice(PLAINTEXT)).unwrap();
+    let plain_addr = plain.read().unwrap().as_ptr() as usize;
+    assert_eq!(common::peek(plain_addr, 32), PLAINTEXT);
+
+    let mut secret = EncryptedSecret::<32>::new_with(|b| b.copy_from_slice(PLAINTEXT)).unwrap();
+    let addr = {
+        let view = secret.read().unwrap();
+        // While the guard is alive the page holds plaintext.
+        assert_eq!(common::peek(view.as_ptr() as usize, 32), PLAINTEXT);
+        view.as_ptr() as usize
+    };
+    let sealed = common::peek(addr, 32);
+    assert_ne!(sealed, PLAINTEXT, "sealed page must not hold plaintext");
+
+    // A write guard re-encrypts under a fresh nonce, so even identical
+    // plaintext produces different ciphertext.
+    drop(secret.write().unwrap());
+    assert_ne!(
+        common::peek(addr, 32),
+        sealed,
+        "write must reseal under a new nonce"
+    );
+    assert_eq!(&secret.read().unwrap()[..], PLAINTEXT);
+}
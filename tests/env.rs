@@ -0,0 +1,108 @@
+//! `Secret::from_env`: copy, overwrite, unset. The environment is process
+//! global, so every test holds `ENV` while it touches it.
+#![cfg(unix)]
+
+use std::io::ErrorKind;
+use std::process::Command;
+use std::sync::Mutex;
+
+use memsafe::Secret;
+
+static ENV: Mutex<()> = Mutex::new(());
+
+#[test]
+fn value_is_moved_into_the_secret_and_unset() {
+    let _env = ENV.lock().unwrap();
+    unsafe { std::env::set_var("MEMSAFE_TEST_MOVED", "s3cr3t") };
+
+    let (mut secret, scrub) = unsafe { Secret::<16>::from_env("MEMSAFE_TEST_MOVED") }.unwrap();
+    assert_eq!(&secret.read().unwrap()[..6], b"s3cr3t");
+    assert!(secret.read().unwrap()[6..].iter().all(|&b| b == 0));
+    assert!(scrub.is_complete(), "{scrub:?}");
+    assert!(std::env::var_os("MEMSAFE_TEST_MOVED").is_none());
+}
+
+#[test]
+fn errors_leave_the_environment_untouched() {
+    let _env = ENV.lock().unwrap();
+    unsafe { std::env::remove_var("MEMSAFE_TEST_MISSING") };
+    let err = unsafe { Secret::<16>::from_env("MEMSAFE_TEST_MISSING") }
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+
+    for name in ["", "A=B", "A\0B"] {
+        let err = unsafe { Secret::<16>::from_env(name) }.err().unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::InvalidInput, "name {name:?}");
+    }
+
+    unsafe { std::env::set_var("MEMSAFE_TEST_LONG", "0123456789") };
+    let err = unsafe { Secret::<8>::from_env("MEMSAFE_TEST_LONG") }
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    assert_eq!(std::env::var("MEMSAFE_TEST_LONG").unwrap(), "0123456789");
+    unsafe { std::env::remove_var("MEMSAFE_TEST_LONG") };
+}
+
+#[test]
+fn prefix_of_another_name_does_not_match() {
+    let _env = ENV.lock().unwrap();
+    unsafe { std::env::set_var("MEMSAFE_TEST_PREFIX_LONGER", "other") };
+    let err = unsafe { Secret::<16>::from_env("MEMSAFE_TEST_PREFIX") }
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+    assert_eq!(
+        std::env::var("MEMSAFE_TEST_PREFIX_LONGER").unwrap(),
+        "other"
+    );
+    unsafe { std::env::remove_var("MEMSAFE_TEST_PREFIX_LONGER") };
+}
+
+/// Run by `inherited_value_is_cleared_from_proc_environ` in a child that
+/// inherits `MEMSAFE_TEST_INHERITED`; ignored in a normal run.
+#[cfg(target_os = "linux")]
+#[test]
+#[ignore]
+fn take_inherited_variable() {
+    if std::env::var_os("MEMSAFE_TEST_INHERITED").is_none() {
+        return;
+    }
+    let replace = std::env::var_os("MEMSAFE_TEST_REPLACE").is_some();
+    if replace {
+        unsafe { std::env::set_var("MEMSAFE_TEST_INHERITED", "replaced") };
+    }
+    let (_secret, scrub) = unsafe { Secret::<32>::from_env("MEMSAFE_TEST_INHERITED") }.unwrap();
+    assert!(scrub.unset);
+
+    let environ = std::fs::read("/proc/self/environ").unwrap();
+    let leaked = environ
+        .windows(b"inherited-secret".len())
+        .any(|w| w == b"inherited-secret");
+    // A value replaced with `setenv` leaves the inherited copy behind, and
+    // the report must say so.
+    assert_eq!(leaked, replace);
+    assert_eq!(scrub.overwritten, !replace);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inherited_value_is_cleared_from_proc_environ() {
+    let _env = ENV.lock().unwrap();
+    for replace in [false, true] {
+        let mut child = Command::new(std::env::current_exe().unwrap());
+        child
+            .args(["--exact", "take_inherited_variable", "--ignored"])
+            .env("MEMSAFE_TEST_INHERITED", "inherited-secret");
+        if replace {
+            child.env("MEMSAFE_TEST_REPLACE", "1");
+        }
+        let output = child.output().unwrap();
+        assert!(
+            output.status.success(),
+            "replace = {replace}: {}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! `SecretFile`, `Secret::from_file` and systemd credentials, against files
+//! in a per-test temporary directory.
+#![cfg(unix)]
+
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use memsafe::Secret;
+use memsafe::file::{self, SecretFile};
+
+/// A fresh directory for `test`, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(test: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("memsafe-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn write(&self, name: &str, contents: &[u8], mode: u32) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, contents).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn reads_fixed_and_variable_length() {
+    let dir = TempDir::new("read");
+    let path = dir.write("key", b"0123456789", 0o600);
+
+    let mut secret = Secret::<16>::from_file(&path).unwrap();
+    assert_eq!(&secret.read().unwrap()[..], b"0123456789\0\0\0\0\0\0");
+
+    let mut bytes = SecretFile::new(&path).read_bytes(64).unwrap();
+    assert_eq!(&*bytes.read().unwrap(), b"0123456789");
+
+    let err = Secret::<8>::from_file(&path).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    assert!(path.exists());
+}
+
+#[test]
+fn require_private_refuses_shared_files() {
+    let dir = TempDir::new("private");
+    for mode in [0o640, 0o604, 0o644] {
+        let path = dir.write("shared", b"secret", mode);
+        let err = SecretFile::new(&path)
+            .require_private()
+            .read::<16>()
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.inner().kind(),
+            ErrorKind::PermissionDenied,
+            "mode {mode:o}"
+        );
+    }
+    let path = dir.write("private", b"secret", 0o600);
+    assert!(
+        SecretFile::new(&path)
+            .require_private()
+            .read::<16>()
+            .is_ok()
+    );
+    // Without the option the mode is not checked.
+    let path = dir.write("shared", b"secret", 0o644);
+    assert!(SecretFile::new(&path).read::<16>().is_ok());
+}
+
+#[test]
+fn shred_after_read_zeroes_and_unlinks() {
+    let dir = TempDir::new("shred");
+    let path = dir.write("one-shot", b"use me once", 0o600);
+    // A second link keeps the inode reachable, to check it was zeroed.
+    let witness = dir.0.join("witness");
+    std::fs::hard_link(&path, &witness).unwrap();
+
+    let mut secret = SecretFile::new(&path)
+        .shred_after_read()
+        .read_bytes(64)
+        .unwrap();
+    assert_eq!(&*secret.read().unwrap(), b"use me once");
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&witness).unwrap(), vec![0u8; 11]);
+}
+
+#[test]
+fn failed_read_does_not_shred() {
+    let dir = TempDir::new("no-shred");
+    let path = dir.write("too-long", b"0123456789", 0o600);
+    let err = SecretFile::new(&path)
+        .shred_after_read()
+        .read::<4>()
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    assert_eq!(std::fs::read(&path).unwrap(), b"0123456789");
+}
+
+#[test]
+fn shredding_refuses_symlinks() {
+    let dir = TempDir::new("shred-symlink");
+    let target = dir.write("target", b"not yours", 0o600);
+    let link = dir.0.join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let err = SecretFile::new(&link)
+        .shred_after_read()
+        .read_bytes(64)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().raw_os_error(), Some(libc::ELOOP));
+    assert_eq!(std::fs::read(&target).unwrap(), b"not yours");
+    assert!(link.symlink_metadata().is_ok());
+
+    // Without shredding, symlinks are followed as before.
+    let mut secret = SecretFile::new(&link).read_bytes(64).unwrap();
+    assert_eq!(&*secret.read().unwrap(), b"not yours");
+}
+
+/// The only test that touches `CREDENTIALS_DIRECTORY`.
+#[test]
+fn systemd_credentials_resolve_by_name() {
+    let dir = TempDir::new("credentials");
+    dir.write("db-password", b"hunter2", 0o400);
+    unsafe { std::env::set_var("CREDENTIALS_DIRECTORY", &dir.0) };
+
+    let mut password = file::credential("db-password", 128).unwrap();
+    assert_eq!(&*password.read().unwrap(), b"hunter2");
+    for name in ["", ".", "..", "../etc/passwd", "a/b"] {
+        let err = file::credential_path(name).err().unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::InvalidInput, "name {name:?}");
+    }
+    let err = file::credential("missing", 128).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+
+    unsafe { std::env::remove_var("CREDENTIALS_DIRECTORY") };
+    let err = file::credential("db-password", 128).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+}
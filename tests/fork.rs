@@ -0,0 +1,56 @@
+//! `ForkDetector`: armed in the creating process, tripped in a forked child.
+
+mod common;
+
+use memsafe::fork::ForkDetector;
+
+#[test]
+fn not_forked_in_the_creating_process() {
+    let mut detector = ForkDetector::new().unwrap();
+    assert!(!detector.has_forked());
+    detector.rearm().unwrap();
+    assert!(!detector.has_forked());
+}
+
+#[test]
+fn usable_from_other_threads() {
+    let detector = ForkDetector::new().unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| assert!(!detector.has_forked()));
+    });
+}
+
+/// The child must see the fork, re-arm, and then see none; the parent must
+/// see none throughout. The child reports through its exit code and makes
+/// only syscalls, because forking a threaded test runner allows nothing
+/// more.
+#[cfg(unix)]
+#[test]
+fn forked_child_detects_the_fork_and_can_rearm() {
+    if common::emulated_kernel() {
+        eprintln!("skipping: qemu user-mode emulation does not reproduce fork/madvise semantics");
+        return;
+    }
+
+    let mut detector = ForkDetector::new().unwrap();
+    match unsafe { libc::fork() } {
+        0 => {
+            let code = if !detector.has_forked() {
+                1
+            } else if detector.rearm().is_err() || detector.has_forked() {
+                2
+            } else {
+                0
+            };
+            unsafe { libc::_exit(code) };
+        }
+        pid if pid > 0 => {
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            assert!(libc::WIFEXITED(status), "child did not exit normally");
+            assert_eq!(libc::WEXITSTATUS(status), 0, "child exit code");
+            assert!(!detector.has_forked());
+        }
+        _ => panic!("fork failed"),
+    }
+}
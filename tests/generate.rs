@@ -0,0 +1,134 @@
+//! Tests for password and token generation.
+
+use memsafe::Secret;
+use memsafe::generate::{self, CharClass, TokenFormat};
+
+#[test]
+fn password_has_requested_length_and_alphabet() {
+    let mut pw = generate::password(32, &[CharClass::optional(generate::LOWERCASE)]).unwrap();
+    assert_eq!(pw.len(), 32);
+    let view = pw.read().unwrap();
+    assert!(view.bytes().all(|c| c.is_ascii_lowercase()));
+}
+
+#[test]
+fn password_contains_every_required_class() {
+    // With 4 characters and 4 required classes, each class must appear
+    // exactly once; repeat to make a missing-class bug show up.
+    for _ in 0..200 {
+        let mut pw = generate::password(
+            4,
+            &[
+                CharClass::required(generate::LOWERCASE),
+                CharClass::required(generate::UPPERCASE),
+                CharClass::required(generate::DIGITS),
+                CharClass::required(generate::SYMBOLS),
+            ],
+        )
+        .unwrap();
+        let view = pw.read().unwrap();
+        assert!(
+            view.bytes().any(|c| c.is_ascii_lowercase()),
+            "{}",
+            view.len()
+        );
+        assert!(view.bytes().any(|c| c.is_ascii_uppercase()));
+        assert!(view.bytes().any(|c| c.is_ascii_digit()));
+        assert!(view.bytes().any(|c| generate::SYMBOLS.contains(&c)));
+    }
+}
+
+#[test]
+fn too_short_for_required_classes_is_rejected() {
+    let err = generate::password(
+        1,
+        &[
+            CharClass::required(generate::LOWERCASE),
+            CharClass::required(generate::DIGITS),
+        ],
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn empty_or_non_ascii_classes_are_rejected() {
+    for classes in [
+        &[][..],
+        &[CharClass::optional(b"")][..],
+        &[CharClass::optional("é".as_bytes())][..],
+    ] {
+        let err = generate::password(8, classes).err().unwrap();
+        assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn tokens_use_their_format_alphabet() {
+    for format in [
+        TokenFormat::Hex,
+        TokenFormat::Base32,
+        TokenFormat::Base64Url,
+    ] {
+        let mut token = generate::token(format, 64).unwrap();
+        let view = token.read().unwrap();
+        assert_eq!(view.len(), 64);
+        assert!(view.bytes().all(|c| format.alphabet().contains(&c)));
+    }
+}
+
+#[test]
+fn overlapping_classes_do_not_bias_the_distribution() {
+    // "ab" listed twice and "c" once: a de-duplication bug would make `a`
+    // and `b` twice as likely as `c`. Each character is expected 30k times
+    // out of 90k; the standard deviation is about 141, so 29k..31k is a
+    // seven-sigma window, while the biased split would give 36k/36k/18k.
+    let mut counts = [0usize; 3];
+    for _ in 0..30 {
+        let mut pw = generate::password(
+            3000,
+            &[
+                CharClass::optional(b"ab"),
+                CharClass::optional(b"ab"),
+                CharClass::optional(b"c"),
+            ],
+        )
+        .unwrap();
+        for c in pw.read().unwrap().bytes() {
+            counts[(c - b'a') as usize] += 1;
+        }
+    }
+    for count in counts {
+        assert!((29_000..31_000).contains(&count), "counts: {counts:?}");
+    }
+}
+
+#[test]
+fn fill_generates_into_an_existing_secret() {
+    let mut pin = Secret::<8>::new_with(|_| {}).unwrap();
+    generate::fill(
+        &mut pin.write().unwrap()[..],
+        &[CharClass::required(generate::DIGITS)],
+    )
+    .unwrap();
+    assert!(pin.read().unwrap().iter().all(u8::is_ascii_digit));
+}
+
+#[test]
+fn many_small_required_classes_terminate() {
+    // One character from each of ten single-character classes: a random
+    // draw qualifies about once in 1e13 tries.
+    let classes: Vec<_> = generate::DIGITS
+        .chunks(1)
+        .map(CharClass::required)
+        .collect();
+    for length in [10, 11, 300] {
+        let mut pw = generate::password(length, &classes).unwrap();
+        let view = pw.read().unwrap();
+        assert_eq!(view.len(), length);
+        for digit in generate::DIGITS {
+            assert!(view.bytes().any(|c| c == *digit));
+        }
+    }
+}
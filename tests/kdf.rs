@@ -0,0 +1,88 @@
+//! Known-answer tests for HMAC, HKDF and PBKDF2, with keys passed from
+//! guards of protected secrets.
+
+use memsafe::{Secret, SecretBytes, kdf};
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn hmac_of_a_guarded_key_matches_rfc4231() {
+    let mut key = Secret::<20>::new_with(|k| k.fill(0x0b)).unwrap();
+    let mut mac = kdf::hmac_sha256(&key.read().unwrap()[..], b"Hi There").unwrap();
+    assert_eq!(
+        mac.read().unwrap().to_vec(),
+        hex("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7")
+    );
+}
+
+#[test]
+fn hkdf_matches_rfc5869_test_case_1() {
+    let salt: Vec<u8> = (0..13).collect();
+    let info: Vec<u8> = (0xf0..0xfa).collect();
+    let mut ikm = Secret::<22>::new_with(|k| k.fill(0x0b)).unwrap();
+
+    let mut prk = kdf::hkdf_extract(&salt, &ikm.read().unwrap()[..]).unwrap();
+    assert_eq!(
+        prk.read().unwrap().to_vec(),
+        hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+    );
+
+    let expected =
+        hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+    let mut okm: Secret<42> = kdf::hkdf_expand(&prk.read().unwrap()[..], &info).unwrap();
+    assert_eq!(okm.read().unwrap().to_vec(), expected);
+    let mut okm = kdf::hkdf_expand_bytes(&prk.read().unwrap()[..], &info, 42).unwrap();
+    assert_eq!(okm.read().unwrap().to_vec(), expected);
+}
+
+#[test]
+fn hkdf_expand_reaches_its_maximum_length() {
+    let mut okm = kdf::hkdf_expand_bytes(&[b'k'; 32], b"x", kdf::HKDF_MAX_LEN).unwrap();
+    assert_eq!(
+        okm.read().unwrap()[kdf::HKDF_MAX_LEN - 32..].to_vec(),
+        hex("6a87139097842d0f935eb4a0713741c4b838153e13b8f9c7331478dec9fa8155")
+    );
+}
+
+#[test]
+fn hkdf_rejects_short_prk_and_long_output() {
+    let err = kdf::hkdf_expand::<32>(&[0; 31], b"").err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+    let err = kdf::hkdf_expand_bytes(&[0; 32], b"", kdf::HKDF_MAX_LEN + 1)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn pbkdf2_matches_known_vectors() {
+    // RFC 7914 section 11, two output blocks.
+    let mut dk: Secret<64> = kdf::pbkdf2_sha256(b"passwd", b"salt", 1).unwrap();
+    assert_eq!(
+        dk.read().unwrap().to_vec(),
+        hex(
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+             49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        )
+    );
+
+    // A truncated block after many iterations, from a guarded password.
+    let mut password = SecretBytes::from_bytes(b"password".to_vec()).unwrap();
+    let mut dk =
+        kdf::pbkdf2_sha256_bytes(&password.read().unwrap()[..], b"salt", 4096, 20).unwrap();
+    assert_eq!(
+        dk.read().unwrap().to_vec(),
+        hex("c5e478d59288c841aa530db6845c4c8d962893a0")
+    );
+}
+
+#[test]
+fn pbkdf2_rejects_zero_iterations() {
+    let err = kdf::pbkdf2_sha256::<32>(b"pw", b"salt", 0).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
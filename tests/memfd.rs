@@ -0,0 +1,114 @@
+//! `memfd`: handing secrets over a Unix socket in sealed memfds.
+#![cfg(target_os = "linux")]
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use memsafe::{Secret, SecretBytes, SecretString, memfd};
+
+/// Send `fd` with `SCM_RIGHTS` the way `memfd::send` frames it, without
+/// sealing anything.
+fn send_raw_fd(socket: &UnixStream, fd: RawFd) {
+    let mut byte = [b'M'];
+    let mut iov = libc::iovec {
+        iov_base: byte.as_mut_ptr().cast(),
+        iov_len: 1,
+    };
+    let mut control = [0u64; 4];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(4) } as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(4) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+        assert_eq!(libc::sendmsg(socket.as_raw_fd(), &msg, 0), 1);
+    }
+}
+
+#[test]
+fn every_secret_type_arrives_intact() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let sender = thread::spawn(move || {
+        let mut key = Secret::<32>::new_with(|k| k.fill(0xa5)).unwrap();
+        memfd::send(&a, &mut key).unwrap();
+        let mut blob = SecretBytes::new_with(5000, |b| b.fill(7)).unwrap();
+        memfd::send(&a, &mut blob).unwrap();
+        let mut token = SecretString::try_from("tok").unwrap();
+        memfd::send(&a, &mut token).unwrap();
+    });
+
+    let mut key: Secret<32> = memfd::recv(&b).unwrap();
+    assert_eq!(*key.read().unwrap(), [0xa5; 32]);
+    let mut blob = memfd::recv_bytes(&b, 8192).unwrap();
+    assert_eq!(blob.len(), 5000);
+    assert!(blob.read().unwrap().iter().all(|&x| x == 7));
+    let mut padded: Secret<8> = memfd::recv(&b).unwrap();
+    assert_eq!(&*padded.read().unwrap(), b"tok\0\0\0\0\0");
+    sender.join().unwrap();
+}
+
+#[test]
+fn oversized_secret_is_refused_and_the_sender_told() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let sender = thread::spawn(move || {
+        let mut key = Secret::<64>::random().unwrap();
+        memfd::send(&a, &mut key)
+    });
+    let err = memfd::recv::<32>(&b).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    let err = sender.join().unwrap().err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn unsealed_descriptors_and_plain_bytes_are_refused() {
+    let (a, b) = UnixStream::pair().unwrap();
+
+    let fd = unsafe { libc::memfd_create(c"unsealed".as_ptr(), libc::MFD_CLOEXEC) };
+    assert!(fd >= 0);
+    assert_eq!(unsafe { libc::write(fd, b"key".as_ptr().cast(), 3) }, 3);
+    send_raw_fd(&a, fd);
+    unsafe { libc::close(fd) };
+    let err = memfd::recv_bytes(&b, 64).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+
+    send_raw_fd(&a, libc::STDIN_FILENO);
+    let err = memfd::recv_bytes(&b, 64).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+
+    (&a).write_all(b"M").unwrap();
+    let err = memfd::recv_bytes(&b, 64).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+
+    // Each refusal was answered.
+    let mut answers = [9u8; 3];
+    (&a).read_exact(&mut answers).unwrap();
+    assert_eq!(answers, [0, 0, 0]);
+}
+
+#[test]
+fn peer_hanging_up_is_an_error() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let sender = thread::spawn(move || {
+        let mut key = Secret::<16>::random().unwrap();
+        memfd::send(&a, &mut key)
+    });
+    // Read the handoff without answering, then hang up.
+    let mut byte = [0u8; 1];
+    (&b).read_exact(&mut byte).unwrap();
+    drop(b);
+    let err = sender.join().unwrap().err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::UnexpectedEof);
+
+    let (a, b) = UnixStream::pair().unwrap();
+    drop(a);
+    let err = memfd::recv_bytes(&b, 16).err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::UnexpectedEof);
+}
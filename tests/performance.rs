@@ -6,19 +6,11 @@
 //! on pathological regressions: an accidental allocation per read, a leaked
 //! page per construction, a syscall storm.
 
+mod common;
+
 use memsafe::Secret;
 use std::time::Instant;
 
-/// True under the cross/qemu CI targets, where syscall overhead is not
-/// representative of any real deployment.
-fn emulated_kernel() -> bool {
-    std::env::vars().any(|(k, _)| {
-        k == "QEMU_LD_PREFIX"
-            || k == "CROSS_RUNNER"
-            || (k.starts_with("CARGO_TARGET_") && k.ends_with("_RUNNER"))
-    })
-}
-
 #[test]
 fn construction_throughput_smoke() {
     const OPS: u32 = 500;
@@ -32,7 +24,7 @@ fn construction_throughput_smoke() {
         "construction: {OPS} create+drop cycles in {elapsed:?} ({:?}/op)",
         elapsed / OPS
     );
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         return;
     }
     // Typical: well under 50ms total. Budget: 15s.
@@ -56,7 +48,7 @@ fn read_guard_cycle_smoke() {
         "read guard: {OPS} unseal+read+reseal cycles in {elapsed:?} ({:?}/op)",
         elapsed / OPS
     );
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         return;
     }
     // Two mprotect calls per cycle; typically tens of ms total. Budget: 15s.
@@ -80,7 +72,7 @@ fn write_guard_cycle_smoke() {
         "write guard: {OPS} unseal+write+reseal cycles in {elapsed:?} ({:?}/op)",
         elapsed / OPS
     );
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         return;
     }
     assert!(
@@ -102,7 +94,7 @@ fn from_bytes_throughput_smoke() {
         "from_bytes: {OPS} ingest+drop cycles in {elapsed:?} ({:?}/op)",
         elapsed / OPS
     );
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         return;
     }
     assert!(
@@ -0,0 +1,235 @@
+//! `prompt`: driven through a pseudo-terminal. The test plays the user on
+//! the master side; the prompt runs on the slave side in another thread.
+#![cfg(unix)]
+
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use memsafe::prompt::Prompt;
+
+/// A pty pair: the master end and the path of the slave. The returned
+/// slave handle keeps the pty alive between prompts; without it, reading
+/// the master fails with `EIO` whenever no prompt has the slave open.
+fn open_pty() -> (File, String, File) {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0, "posix_openpt failed");
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        // `ptsname` returns a shared static buffer; tests run in parallel.
+        let mut name = [0 as libc::c_char; 128];
+        assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+        let path = std::ffi::CStr::from_ptr(name.as_ptr())
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let slave = File::options()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&path)
+            .unwrap();
+        (File::from_raw_fd(master), path, slave)
+    }
+}
+
+/// Read from the master until `text` has been shown, returning everything
+/// the terminal printed so far.
+fn wait_for(master: &mut File, text: &str) -> String {
+    let mut seen = Vec::new();
+    let mut byte = [0u8; 1];
+    while !String::from_utf8_lossy(&seen).contains(text) {
+        assert_eq!(master.read(&mut byte).unwrap(), 1);
+        seen.push(byte[0]);
+    }
+    String::from_utf8(seen).unwrap()
+}
+
+fn echo_enabled(path: &str) -> bool {
+    let tty = File::options()
+        .read(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(path)
+        .unwrap();
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::tcgetattr(tty.as_raw_fd(), &mut termios) }, 0);
+    termios.c_lflag & libc::ECHO != 0
+}
+
+#[test]
+fn reads_without_echo_and_restores_the_terminal() {
+    let (mut master, path, _slave) = open_pty();
+    assert!(echo_enabled(&path));
+
+    let prompt = Prompt::new("Passphrase: ").tty(&path);
+    let reader = thread::spawn(move || prompt.read_string());
+    wait_for(&mut master, "Passphrase: ");
+    master.write_all(b"hunter2\r").unwrap();
+
+    let mut secret = reader.join().unwrap().unwrap();
+    assert_eq!(&*secret.read().unwrap(), "hunter2");
+    let shown = wait_for(&mut master, "\n");
+    assert!(!shown.contains("hunter2"), "input was echoed: {shown:?}");
+    assert!(echo_enabled(&path));
+}
+
+#[test]
+fn backspace_removes_whole_characters_and_ctrl_u_clears() {
+    let (mut master, path, _slave) = open_pty();
+    let prompt = Prompt::new("> ").tty(&path);
+    let reader = thread::spawn(move || prompt.read_string());
+    wait_for(&mut master, "> ");
+    // "xyz", Ctrl-U, "pässX", DEL, "wort", Backspace past nothing else.
+    master
+        .write_all("xyz\x15päss\x7f\x7fsX\x08wort\n".as_bytes())
+        .unwrap();
+
+    let mut secret = reader.join().unwrap().unwrap();
+    assert_eq!(&*secret.read().unwrap(), "päswort");
+}
+
+#[test]
+fn confirmation_must_match() {
+    let (mut master, path, _slave) = open_pty();
+    let prompt = Prompt::new("New: ").confirm("Again: ").tty(&path);
+    let reader = thread::spawn(move || prompt.read_bytes());
+    wait_for(&mut master, "New: ");
+    master.write_all(b"secret\n").unwrap();
+    wait_for(&mut master, "Again: ");
+    master.write_all(b"secret\n").unwrap();
+    let mut secret = reader.join().unwrap().unwrap();
+    assert_eq!(&*secret.read().unwrap(), b"secret");
+
+    let prompt = Prompt::new("New: ").confirm("Again: ").tty(&path);
+    let reader = thread::spawn(move || prompt.read_bytes());
+    wait_for(&mut master, "New: ");
+    master.write_all(b"secret\n").unwrap();
+    wait_for(&mut master, "Again: ");
+    master.write_all(b"secrex\n").unwrap();
+    let err = reader.join().unwrap().err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidData);
+    assert!(echo_enabled(&path));
+}
+
+#[test]
+fn overlong_empty_and_aborted_entries_are_rejected() {
+    let (mut master, path, _slave) = open_pty();
+    let cases: [(&[u8], ErrorKind); 3] = [
+        (b"12345\n", ErrorKind::InvalidInput),
+        (b"\n", ErrorKind::InvalidInput),
+        (b"\x04", ErrorKind::UnexpectedEof),
+    ];
+    for (input, expected) in cases {
+        let prompt = Prompt::new("> ").max_len(4).tty(&path);
+        let reader = thread::spawn(move || prompt.read_bytes());
+        wait_for(&mut master, "> ");
+        master.write_all(input).unwrap();
+        let err = reader.join().unwrap().err().unwrap();
+        assert_eq!(err.inner().kind(), expected, "input {input:?}");
+        assert!(echo_enabled(&path));
+    }
+
+    // Exactly at the limit is accepted.
+    let prompt = Prompt::new("> ").max_len(4).tty(&path);
+    let reader = thread::spawn(move || prompt.read_bytes());
+    wait_for(&mut master, "> ");
+    master.write_all(b"1234\n").unwrap();
+    assert_eq!(reader.join().unwrap().unwrap().len(), 4);
+}
+
+#[test]
+fn unrepresentable_max_len_is_rejected() {
+    let (_master, path, _slave) = open_pty();
+    let err = Prompt::new("> ")
+        .max_len(usize::MAX)
+        .tty(&path)
+        .read_bytes()
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    assert!(echo_enabled(&path));
+}
+
+#[test]
+fn not_a_terminal_is_an_error() {
+    assert!(Prompt::new("> ").tty("/dev/null").read_bytes().is_err());
+}
+
+/// Run by `signal_restores_the_terminal` in a child process, which it then
+/// kills; ignored in a normal run.
+#[test]
+#[ignore]
+fn prompt_until_killed() {
+    if let Ok(path) = std::env::var("MEMSAFE_PROMPT_TTY") {
+        let _ = Prompt::new("Waiting: ").tty(path).read_bytes();
+    }
+}
+
+#[test]
+fn signal_restores_the_terminal() {
+    let (mut master, path, _slave) = open_pty();
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "prompt_until_killed", "--ignored"])
+        .env("MEMSAFE_PROMPT_TTY", &path)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for(&mut master, "Waiting: ");
+    assert!(!echo_enabled(&path));
+
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+    assert!(echo_enabled(&path));
+}
+
+/// Run by `signal_with_a_returning_handler_ends_the_prompt` in a child
+/// process; ignored in a normal run.
+#[test]
+#[ignore]
+fn prompt_with_noop_sighup_handler() {
+    extern "C" fn noop(_: libc::c_int) {}
+
+    if let Ok(path) = std::env::var("MEMSAFE_PROMPT_TTY") {
+        unsafe { libc::signal(libc::SIGHUP, noop as extern "C" fn(libc::c_int) as usize) };
+        let err = Prompt::new("Waiting: ")
+            .tty(path)
+            .read_bytes()
+            .err()
+            .unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::Interrupted);
+    }
+}
+
+#[test]
+fn signal_with_a_returning_handler_ends_the_prompt() {
+    let (mut master, path, _slave) = open_pty();
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "prompt_with_noop_sighup_handler", "--ignored"])
+        .env("MEMSAFE_PROMPT_TTY", &path)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for(&mut master, "Waiting: ");
+    assert!(!echo_enabled(&path));
+
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGHUP) };
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if std::time::Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("prompt kept reading after the signal");
+        }
+        thread::sleep(std::time::Duration::from_millis(20));
+    };
+    assert!(status.success(), "{status:?}");
+    assert!(echo_enabled(&path));
+}
@@ -0,0 +1,37 @@
+//! Tests for secrets generated straight from the OS CSPRNG.
+
+use memsafe::{Secret, SecretBytes};
+
+#[test]
+fn random_secret_is_not_all_zero() {
+    // 2^-256 false-failure probability.
+    let mut key = Secret::<32>::random().unwrap();
+    assert!(key.read().unwrap().iter().any(|&b| b != 0));
+}
+
+#[test]
+fn random_secrets_differ() {
+    let mut a = Secret::<32>::random().unwrap();
+    let mut b = Secret::<32>::random().unwrap();
+    assert!(!a.ct_eq(&mut b).unwrap());
+}
+
+#[test]
+fn random_fills_every_byte_of_a_large_secret() {
+    // getrandom(2) only promises to fill requests of up to 256 bytes in one
+    // call; larger ones may come back short, which the fill loop retries.
+    let mut secret = SecretBytes::random(64 * 1024).unwrap();
+    let view = secret.read().unwrap();
+    assert_eq!(view.len(), 64 * 1024);
+    // Every 256-byte window must contain a nonzero byte; a window of zeros
+    // would mean part of the page was never filled.
+    for window in view.chunks(256) {
+        assert!(window.iter().any(|&b| b != 0));
+    }
+}
+
+#[test]
+fn random_bytes_zero_length_is_rejected() {
+    let err = SecretBytes::random(0).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
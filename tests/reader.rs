@@ -0,0 +1,87 @@
+//! Reading secrets from `io::Read` sources.
+
+use std::io::{self, Read};
+
+use memsafe::{Secret, SecretBytes};
+
+/// Hands out one byte per call and interrupts every other call, then
+/// optionally fails instead of reporting end of input.
+struct Trickle<'a> {
+    data: &'a [u8],
+    calls: usize,
+    fail_at_end: bool,
+}
+
+impl<'a> Trickle<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Trickle {
+            data,
+            calls: 0,
+            fail_at_end: false,
+        }
+    }
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls += 1;
+        if self.calls % 2 == 0 {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        match self.data.split_first() {
+            Some((&b, rest)) => {
+                buf[0] = b;
+                self.data = rest;
+                Ok(1)
+            }
+            None if self.fail_at_end => Err(io::Error::other("device unplugged")),
+            None => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn short_and_interrupted_reads_are_retried() {
+    let mut secret = Secret::<16>::from_reader(&mut Trickle::new(b"hunter2")).unwrap();
+    assert_eq!(&secret.read().unwrap()[..], b"hunter2\0\0\0\0\0\0\0\0\0");
+
+    let mut bytes = SecretBytes::from_reader_limited(&mut Trickle::new(b"hunter2"), 64).unwrap();
+    assert_eq!(&bytes.read().unwrap()[..], b"hunter2");
+}
+
+#[test]
+fn input_filling_the_buffer_exactly_is_accepted() {
+    let mut secret = Secret::<7>::from_reader(&mut &b"hunter2"[..]).unwrap();
+    assert_eq!(&secret.read().unwrap()[..], b"hunter2");
+    let bytes = SecretBytes::from_reader_limited(&mut &b"hunter2"[..], 7).unwrap();
+    assert_eq!(bytes.len(), 7);
+}
+
+#[test]
+fn overlong_input_is_rejected() {
+    let err = Secret::<6>::from_reader(&mut &b"hunter2"[..])
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), io::ErrorKind::InvalidInput);
+    let err = SecretBytes::from_reader_limited(&mut &b"hunter2"[..], 6)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn io_errors_are_reported() {
+    let mut failing = Trickle::new(b"partial");
+    failing.fail_at_end = true;
+    let err = Secret::<32>::from_reader(&mut failing).err().unwrap();
+    assert_eq!(err.inner().kind(), io::ErrorKind::Other);
+    assert!(err.to_string().contains("device unplugged"));
+}
+
+#[test]
+fn empty_input_cannot_make_secret_bytes() {
+    let err = SecretBytes::from_reader_limited(&mut io::empty(), 64)
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,88 @@
+//! `SecureRng`: output, protected state, and reseeding after `fork()`.
+
+mod common;
+
+use memsafe::rng::SecureRng;
+
+#[test]
+fn consecutive_outputs_differ() {
+    let mut rng = SecureRng::new().unwrap();
+    let mut a = [0u8; 64];
+    let mut b = [0u8; 64];
+    rng.fill_bytes(&mut a).unwrap();
+    rng.fill_bytes(&mut b).unwrap();
+    assert_ne!(a, [0; 64]);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn independent_instances_do_not_share_a_stream() {
+    let mut a = SecureRng::new().unwrap();
+    let mut b = SecureRng::new().unwrap();
+    assert_ne!(a.next_u64().unwrap(), b.next_u64().unwrap());
+}
+
+#[test]
+fn large_requests_span_several_keys() {
+    let mut rng = SecureRng::new().unwrap();
+    let mut buf = vec![0u8; (1 << 20) * 2 + 123];
+    rng.fill_bytes(&mut buf).unwrap();
+    // Every 1 MiB segment is keyed differently; no two segment heads match.
+    assert_ne!(buf[..32], buf[1 << 20..(1 << 20) + 32]);
+    assert_ne!(buf[buf.len() - 32..], [0; 32]);
+}
+
+#[test]
+fn secrets_are_drawn_into_protected_pages() {
+    let mut rng = SecureRng::new().unwrap();
+    let mut a = rng.secret::<32>().unwrap();
+    let mut b = rng.secret::<32>().unwrap();
+    assert!(!a.ct_eq(&mut b).unwrap());
+}
+
+/// A forked child must not replay the parent's next output. The child
+/// draws 32 bytes and sends them back through a pipe; it only makes
+/// syscalls (`getrandom`, `mprotect`, `write`, `_exit`) because forking a
+/// threaded test runner allows nothing more.
+#[cfg(target_os = "linux")]
+#[test]
+fn forked_child_reseeds_instead_of_repeating_the_parent() {
+    if common::emulated_kernel() {
+        eprintln!("skipping: qemu user-mode emulation does not reproduce fork/madvise semantics");
+        return;
+    }
+
+    let mut rng = SecureRng::new().unwrap();
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+    match unsafe { libc::fork() } {
+        0 => {
+            let mut out = [0u8; 32];
+            let code = match rng.fill_bytes(&mut out) {
+                Ok(()) => {
+                    let n = unsafe { libc::write(fds[1], out.as_ptr().cast(), out.len()) };
+                    if n == 32 { 0 } else { 2 }
+                }
+                Err(_) => 1,
+            };
+            unsafe { libc::_exit(code) };
+        }
+        pid if pid > 0 => {
+            unsafe { libc::close(fds[1]) };
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+
+            let mut child = [0u8; 32];
+            let n = unsafe { libc::read(fds[0], child.as_mut_ptr().cast(), 32) };
+            unsafe { libc::close(fds[0]) };
+            assert_eq!(n, 32);
+
+            let mut parent = [0u8; 32];
+            rng.fill_bytes(&mut parent).unwrap();
+            assert_ne!(child, parent, "child replayed the parent's stream");
+        }
+        _ => panic!("fork failed"),
+    }
+}
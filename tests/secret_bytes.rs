@@ -0,0 +1,88 @@
+//! Tests for `SecretBytes`, the runtime-length secret. It shares its page
+//! machinery with `Secret<N>`; these tests cover what differs: the length
+//! comes from the caller or the source instead of the type.
+
+use memsafe::SecretBytes;
+
+#[test]
+fn new_with_writes_full_buffer() {
+    let mut secret = SecretBytes::new_with(40, |buf| {
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+    })
+    .unwrap();
+    assert_eq!(secret.len(), 40);
+    let view = secret.read().unwrap();
+    assert_eq!(view.len(), 40);
+    for (i, &b) in view.iter().enumerate() {
+        assert_eq!(b, i as u8);
+    }
+}
+
+#[test]
+fn new_with_no_writes_yields_zero_buffer() {
+    let mut secret = SecretBytes::new_with(16, |_| {}).unwrap();
+    assert!(secret.read().unwrap().iter().all(|&b| b == 0));
+}
+
+#[test]
+fn zero_length_is_rejected() {
+    let err = SecretBytes::new_with(0, |_| {}).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn from_bytes_takes_source_length() {
+    let mut secret = SecretBytes::from_bytes(b"my-api-key".to_vec()).unwrap();
+    assert_eq!(secret.len(), 10);
+    assert!(!secret.is_empty());
+    assert_eq!(&secret.read().unwrap()[..], b"my-api-key");
+}
+
+#[test]
+fn from_bytes_empty_source_is_returned_untouched() {
+    let (returned, err) = match SecretBytes::from_bytes(Vec::<u8>::new()) {
+        Ok(_) => panic!("expected error"),
+        Err(e) => e,
+    };
+    assert!(returned.is_empty());
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn write_then_read_round_trip() {
+    let mut secret = SecretBytes::new_with(7, |buf| buf.copy_from_slice(b"initial")).unwrap();
+    secret.write().unwrap().copy_from_slice(b"updated");
+    assert_eq!(&secret.read().unwrap()[..], b"updated");
+}
+
+#[test]
+fn spans_multiple_pages() {
+    let mut secret = SecretBytes::new_with(3 * 4096 + 17, |buf| buf.fill(0x5A)).unwrap();
+    let view = secret.read().unwrap();
+    assert_eq!(view.len(), 3 * 4096 + 17);
+    assert!(view.iter().all(|&b| b == 0x5A));
+}
+
+#[test]
+fn panic_in_init_unwinds_cleanly() {
+    let result = std::panic::catch_unwind(|| {
+        SecretBytes::new_with(32, |buf| {
+            buf[..3].copy_from_slice(b"abc");
+            panic!("simulated init failure");
+        })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn secret_bytes_is_send_across_threads() {
+    let mut secret = SecretBytes::from_bytes(b"moved".to_vec()).unwrap();
+    let handle = std::thread::spawn(move || {
+        assert_eq!(&secret.read().unwrap()[..], b"moved");
+        secret
+    });
+    let mut secret = handle.join().unwrap();
+    assert_eq!(&secret.read().unwrap()[..], b"moved");
+}
@@ -0,0 +1,42 @@
+//! Tests for `SecretString`: UTF-8 validation at construction and the `str`
+//! guards on top of the `SecretBytes` page.
+
+use memsafe::SecretString;
+
+#[test]
+fn try_from_str_round_trips() {
+    let mut secret = SecretString::try_from("héllo-🔒").unwrap();
+    assert_eq!(secret.len(), "héllo-🔒".len());
+    assert_eq!(&*secret.read().unwrap(), "héllo-🔒");
+}
+
+#[test]
+fn try_from_string_round_trips() {
+    let mut secret = SecretString::try_from(String::from("owned")).unwrap();
+    assert_eq!(&*secret.read().unwrap(), "owned");
+}
+
+#[test]
+fn try_from_empty_string_returns_it() {
+    let (returned, err) = match SecretString::try_from(String::new()) {
+        Ok(_) => panic!("expected error"),
+        Err(e) => e,
+    };
+    assert!(returned.is_empty());
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn new_with_rejects_invalid_utf8() {
+    let err = SecretString::new_with(2, |buf| buf.copy_from_slice(&[0xC3, 0x28]))
+        .err()
+        .unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn write_guard_allows_utf8_preserving_edits() {
+    let mut secret = SecretString::try_from("lower").unwrap();
+    secret.write().unwrap().make_ascii_uppercase();
+    assert_eq!(&*secret.read().unwrap(), "LOWER");
+}
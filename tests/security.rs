@@ -5,6 +5,8 @@
 //! that a sealed page is truly inaccessible, that sources are really wiped,
 //! and that failures roll back without leaking a live page.
 
+mod common;
+
 use memsafe::Secret;
 
 /// A byte source that records, at drop time, whether its buffer had been
@@ -206,17 +208,6 @@ fn mlock_failure_rolls_back_cleanly() {
     );
 }
 
-/// True when the tests run under a user-mode emulator (the cross/qemu CI
-/// targets), where fork+madvise semantics don't match a real kernel.
-#[cfg(target_os = "linux")]
-fn emulated_kernel() -> bool {
-    std::env::vars().any(|(k, _)| {
-        k == "QEMU_LD_PREFIX"
-            || k == "CROSS_RUNNER"
-            || (k.starts_with("CARGO_TARGET_") && k.ends_with("_RUNNER"))
-    })
-}
-
 /// `MADV_WIPEONFORK` enforcement: a forked child must see a zeroed page, not
 /// a copy of the secret. Without it, fork() hands the child an *unlocked*
 /// copy-on-write copy of the secret that can be swapped or dumped.
@@ -228,7 +219,7 @@ fn emulated_kernel() -> bool {
 #[cfg(target_os = "linux")]
 #[test]
 fn forked_child_sees_wiped_secret() {
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         eprintln!("skipping: qemu user-mode emulation does not reproduce fork/madvise semantics");
         return;
     }
@@ -286,7 +277,7 @@ fn zero_sized_secret_is_rejected() {
 #[cfg(target_os = "linux")]
 #[test]
 fn kernel_reports_locked_nodump_wipeonfork_flags() {
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         eprintln!("skipping: /proc maps under qemu describe the emulator, not the guest");
         return;
     }
@@ -317,7 +308,7 @@ fn kernel_reports_locked_nodump_wipeonfork_flags() {
 #[cfg(target_os = "linux")]
 #[test]
 fn kernel_reports_permission_transitions() {
-    if emulated_kernel() {
+    if common::emulated_kernel() {
         eprintln!("skipping: /proc maps under qemu describe the emulator, not the guest");
         return;
     }
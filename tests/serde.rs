@@ -0,0 +1,96 @@
+//! `serde` feature: deserializing into secrets and the `expose` helper.
+#![cfg(feature = "serde")]
+
+use memsafe::{Secret, SecretBytes, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+struct Config {
+    #[serde(serialize_with = "memsafe::serde::expose")]
+    token: SecretString,
+    #[serde(serialize_with = "memsafe::serde::expose")]
+    blob: SecretBytes,
+    #[serde(serialize_with = "memsafe::serde::expose")]
+    key: Secret<8>,
+    #[serde(default, skip_serializing)]
+    backup: Option<SecretString>,
+}
+
+#[test]
+fn borrowed_owned_and_sequence_input_is_accepted() {
+    // The escape forces serde_json to unescape into a scratch buffer.
+    let json = r#"{"token":"t\u00e9","blob":[1,2,3],"key":"abc","backup":null}"#;
+    let mut config: Config = serde_json::from_str(json).unwrap();
+    assert_eq!(&*config.token.read().unwrap(), "té");
+    assert_eq!(&*config.blob.read().unwrap(), &[1, 2, 3]);
+    assert_eq!(&*config.key.read().unwrap(), b"abc\0\0\0\0\0");
+    assert!(config.backup.is_none());
+
+    let reader = br#"{"token":"plain","blob":"xy","key":[1,2,3,4,5,6,7,8],"backup":"b"}"#;
+    let mut config: Config = serde_json::from_reader(&reader[..]).unwrap();
+    assert_eq!(&*config.token.read().unwrap(), "plain");
+    assert_eq!(&*config.blob.read().unwrap(), b"xy");
+    assert_eq!(&*config.key.read().unwrap(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(&*config.backup.unwrap().read().unwrap(), "b");
+}
+
+#[test]
+fn expose_round_trips() {
+    let json = r#"{"token":"hunter2","blob":[0,255],"key":[1,2,3,4,5,6,7,8]}"#;
+    let config: Config = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_string(&config).unwrap(), json);
+
+    // Serializing leaves the secrets usable.
+    let mut config = config;
+    assert_eq!(&*config.token.read().unwrap(), "hunter2");
+}
+
+#[test]
+fn invalid_input_is_rejected() {
+    let cases = [
+        r#""123456789""#,      // longer than N
+        "[1,2,3,4,5,6,7,8,9]", // sequence longer than N
+        "[1,256]",             // not a byte
+        "42",                  // wrong type
+    ];
+    for json in cases {
+        assert!(serde_json::from_str::<Secret<8>>(json).is_err(), "{json}");
+    }
+    for json in [r#""""#, "[]"] {
+        assert!(serde_json::from_str::<SecretBytes>(json).is_err(), "{json}");
+    }
+    assert!(serde_json::from_str::<SecretString>(r#""""#).is_err());
+    assert!(serde_json::from_str::<SecretString>("[104,105]").is_err());
+}
+
+#[test]
+fn byte_sequences_are_bounded() {
+    let long = format!("[{}]", vec!["7"; memsafe::serde::MAX_SEQ_LEN + 1].join(","));
+    assert!(serde_json::from_str::<SecretBytes>(&long).is_err());
+    let fits = format!("[{}]", vec!["7"; memsafe::serde::MAX_SEQ_LEN].join(","));
+    let mut secret: SecretBytes = serde_json::from_str(&fits).unwrap();
+    assert!(secret.read().unwrap().iter().all(|&b| b == 7));
+}
+
+#[test]
+fn sequence_length_hints_are_capped() {
+    use serde::de::value::{Error, SeqDeserializer};
+
+    /// Three bytes that claim to be `usize::MAX`, as a hostile length
+    /// prefix would.
+    struct Lying(std::vec::IntoIter<u8>);
+
+    impl Iterator for Lying {
+        type Item = u8;
+        fn next(&mut self) -> Option<u8> {
+            self.0.next()
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (usize::MAX, Some(usize::MAX))
+        }
+    }
+
+    let de = SeqDeserializer::<_, Error>::new(Lying(vec![1, 2, 3].into_iter()));
+    let mut secret = SecretBytes::deserialize(de).unwrap();
+    assert_eq!(&*secret.read().unwrap(), &[1, 2, 3]);
+}
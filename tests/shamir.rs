@@ -0,0 +1,68 @@
+//! Shamir secret sharing: any `threshold` shares recover the secret.
+
+use memsafe::{Secret, shamir};
+
+fn secret() -> Secret<16> {
+    Secret::new_with(|b| b.copy_from_slice(b"break-glass-cred")).unwrap()
+}
+
+#[test]
+fn every_threshold_subset_recovers_the_secret() {
+    let mut original = secret();
+    let mut shares: Vec<Secret<17>> = shamir::split(&mut original, 3, 5).unwrap();
+    for a in 0..5 {
+        for b in a + 1..5 {
+            for c in b + 1..5 {
+                let mut subset: Vec<Secret<17>> = [a, b, c]
+                    .iter()
+                    .map(|&i| Secret::new_with(|s| *s = *shares[i].read().unwrap()).unwrap())
+                    .collect();
+                let mut recovered: Secret<16> = shamir::combine(&mut subset).unwrap();
+                assert_eq!(&*recovered.read().unwrap(), b"break-glass-cred");
+            }
+        }
+    }
+}
+
+#[test]
+fn shares_carry_distinct_x_coordinates_and_hide_the_secret() {
+    let mut original = secret();
+    let mut shares: Vec<Secret<17>> = shamir::split(&mut original, 2, 4).unwrap();
+    for (i, share) in shares.iter_mut().enumerate() {
+        let view = share.read().unwrap();
+        assert_eq!(view[0] as usize, i + 1);
+        assert_ne!(&view[1..], b"break-glass-cred");
+    }
+}
+
+#[test]
+fn fewer_than_threshold_shares_do_not_recover_the_secret() {
+    let mut original = secret();
+    let mut shares: Vec<Secret<17>> = shamir::split(&mut original, 3, 3).unwrap();
+    let mut recovered: Secret<16> = shamir::combine(&mut shares[..2]).unwrap();
+    assert_ne!(&*recovered.read().unwrap(), b"break-glass-cred");
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    let mut original = secret();
+    for (threshold, count) in [(1, 3), (4, 3), (2, 256)] {
+        let err = shamir::split::<16, 17>(&mut original, threshold, count)
+            .err()
+            .unwrap();
+        assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn duplicate_or_empty_shares_are_rejected() {
+    let mut original = secret();
+    let mut shares: Vec<Secret<17>> = shamir::split(&mut original, 2, 2).unwrap();
+    let copy = Secret::new_with(|s| *s = *shares[0].read().unwrap()).unwrap();
+    shares[1] = copy;
+    let err = shamir::combine::<16, 17>(&mut shares).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+
+    let err = shamir::combine::<16, 17>(&mut []).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
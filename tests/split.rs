@@ -0,0 +1,58 @@
+//! Tests for `SplitSecret`: the public behavior matches `Secret` even though
+//! the bytes are stored as two shares. The at-rest contents of the shares
+//! are checked by the unit tests in `src/split.rs`.
+
+use memsafe::split::SplitSecret;
+
+#[test]
+fn new_with_round_trips_through_read() {
+    let mut secret = SplitSecret::<32>::new_with(|buf| {
+        buf[..10].copy_from_slice(b"my-api-key");
+    })
+    .unwrap();
+    let view = secret.read().unwrap();
+    assert_eq!(&view[..10], b"my-api-key");
+    assert!(view[10..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn writes_persist_across_reshares() {
+    let mut secret = SplitSecret::<16>::new_with(|_| {}).unwrap();
+    for round in 0u8..20 {
+        {
+            let mut w = secret.write().unwrap();
+            w[round as usize % 16] = round;
+            assert_eq!(w[round as usize % 16], round);
+        }
+        let r = secret.read().unwrap();
+        assert_eq!(r[round as usize % 16], round);
+    }
+}
+
+#[test]
+fn panic_in_init_propagates() {
+    let result = std::panic::catch_unwind(|| {
+        SplitSecret::<32>::new_with(|buf| {
+            buf[..3].copy_from_slice(b"abc");
+            panic!("simulated init failure");
+        })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn zero_sized_split_secret_is_rejected() {
+    let err = SplitSecret::<0>::new_with(|_| {}).err().unwrap();
+    assert_eq!(err.inner().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn split_secret_is_send_across_threads() {
+    let mut secret = SplitSecret::<16>::new_with(|b| b[..5].copy_from_slice(b"moved")).unwrap();
+    let handle = std::thread::spawn(move || {
+        assert_eq!(&secret.read().unwrap()[..5], b"moved");
+        secret
+    });
+    let mut secret = handle.join().unwrap();
+    assert_eq!(&secret.read().unwrap()[..5], b"moved");
+}
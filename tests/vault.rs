@@ -0,0 +1,127 @@
+//! `vault`: keys held by a forked child, used through handles.
+#![cfg(target_os = "linux")]
+
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+
+use memsafe::vault::{MAX_KEY_LEN, Vault};
+use memsafe::{Secret, SecretString, kdf};
+
+#[test]
+fn imported_key_computes_the_same_hmac_as_locally() {
+    let mut vault = Vault::spawn().unwrap();
+    let mut key = Secret::<32>::new_with(|k| k.fill(0x0b)).unwrap();
+    let handle = vault.import(&mut key).unwrap();
+
+    let mut remote = vault.hmac_sha256(handle, b"Hi There").unwrap();
+    let mut local = kdf::hmac_sha256(&key.read().unwrap()[..], b"Hi There").unwrap();
+    assert!(remote.ct_eq(&mut local).unwrap());
+
+    let generated = vault.generate(32).unwrap();
+    assert_ne!(generated, handle);
+    let mut a = vault.hmac_sha256(generated, b"m").unwrap();
+    let mut b = vault.hmac_sha256(generated, b"m").unwrap();
+    assert!(a.ct_eq(&mut b).unwrap());
+    assert!(!a.ct_eq(&mut remote).unwrap());
+}
+
+#[test]
+fn verify_compares_in_the_child() {
+    let mut vault = Vault::spawn().unwrap();
+    let handle = vault
+        .import(&mut SecretString::try_from("hunter2").unwrap())
+        .unwrap();
+    assert!(vault.verify(handle, b"hunter2").unwrap());
+    assert!(!vault.verify(handle, b"hunter3").unwrap());
+    assert!(!vault.verify(handle, b"hunter22").unwrap());
+}
+
+#[test]
+fn key_files_are_read_by_the_child() {
+    let dir = std::env::temp_dir().join(format!("memsafe-vault-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let private = dir.join("private.key");
+    std::fs::write(&private, b"file key").unwrap();
+    std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o600)).unwrap();
+    let shared = dir.join("shared.key");
+    std::fs::write(&shared, b"file key").unwrap();
+    std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let mut vault = Vault::spawn().unwrap();
+    let loaded = vault.load_file(&private, 64);
+    let refused = vault.load_file(&shared, 64);
+    let missing = vault.load_file(dir.join("missing.key"), 64);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(vault.verify(loaded.unwrap(), b"file key").unwrap());
+    assert_eq!(
+        refused.err().unwrap().inner().kind(),
+        ErrorKind::PermissionDenied
+    );
+    assert_eq!(missing.err().unwrap().inner().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn bad_requests_are_reported_and_the_vault_stays_usable() {
+    let mut vault = Vault::spawn().unwrap();
+    let handle = vault.generate(16).unwrap();
+
+    for len in [0, MAX_KEY_LEN + 1] {
+        let err = vault.generate(len).err().unwrap();
+        assert_eq!(err.inner().kind(), ErrorKind::InvalidInput);
+    }
+    let mut big = memsafe::SecretBytes::random(MAX_KEY_LEN + 1).unwrap();
+    assert_eq!(
+        vault.import(&mut big).err().unwrap().inner().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    vault.remove(handle).unwrap();
+    assert_eq!(
+        vault.remove(handle).err().unwrap().inner().kind(),
+        ErrorKind::NotFound
+    );
+    let err = vault.hmac_sha256(handle, b"m").err().unwrap();
+    assert_eq!(err.inner().kind(), ErrorKind::NotFound);
+
+    assert!(vault.generate(16).is_ok());
+}
+
+#[test]
+fn child_exits_with_the_vault_and_a_dead_child_is_an_error() {
+    let vault = Vault::spawn().unwrap();
+    let pid = vault.id() as libc::pid_t;
+    drop(vault);
+    assert_eq!(unsafe { libc::kill(pid, 0) }, -1, "child was not reaped");
+
+    let mut vault = Vault::spawn().unwrap();
+    unsafe { libc::kill(vault.id() as libc::pid_t, libc::SIGKILL) };
+    assert!(vault.generate(32).is_err());
+}
+
+#[cfg(feature = "aead")]
+#[test]
+fn aead_runs_in_the_child() {
+    use memsafe::aead;
+
+    let mut vault = Vault::spawn().unwrap();
+    let mut key = Secret::<32>::random().unwrap();
+    let handle = vault.import(&mut key).unwrap();
+    let nonce = [3u8; aead::NONCE_LEN];
+
+    let mut data = *b"customer record";
+    let tag = vault.seal(handle, &nonce, b"aad", &mut data).unwrap();
+    assert_ne!(&data, b"customer record");
+    let mut local = data;
+    aead::decrypt_in_place(&mut key, &nonce, b"aad", &mut local, &tag).unwrap();
+    assert_eq!(&local, b"customer record");
+
+    vault.open(handle, &nonce, b"aad", &mut data, &tag).unwrap();
+    assert_eq!(&data, b"customer record");
+
+    let err = vault.open(handle, &nonce, b"other", &mut data, &tag);
+    assert_eq!(err.err().unwrap().inner().kind(), ErrorKind::InvalidData);
+    let short = vault.generate(16).unwrap();
+    let err = vault.seal(short, &nonce, b"", &mut data);
+    assert_eq!(err.err().unwrap().inner().kind(), ErrorKind::InvalidInput);
+}
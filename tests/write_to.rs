@@ -0,0 +1,108 @@
+//! `write_to` / `write_to_fd`: partial writes, interruptions and pipes.
+
+use std::io::{self, ErrorKind, Write};
+
+use memsafe::error::MemoryError;
+use memsafe::{Secret, SecretBytes, SecretString};
+
+/// Accepts at most one byte per call and fails every other call with
+/// `Interrupted`.
+struct Trickle {
+    out: Vec<u8>,
+    calls: usize,
+    flushed: bool,
+}
+
+impl Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.calls += 1;
+        if self.calls % 2 == 0 {
+            return Err(ErrorKind::Interrupted.into());
+        }
+        self.out.push(buf[0]);
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed = true;
+        Ok(())
+    }
+}
+
+#[test]
+fn partial_and_interrupted_writes_are_retried() {
+    let mut secret = Secret::<8>::new_with(|b| b.copy_from_slice(b"8 bytes!")).unwrap();
+    let mut sink = Trickle {
+        out: Vec::new(),
+        calls: 0,
+        flushed: false,
+    };
+    secret.write_to(&mut sink).unwrap();
+    assert_eq!(sink.out, b"8 bytes!");
+    assert!(sink.flushed);
+}
+
+#[test]
+fn every_secret_type_writes_its_bytes() {
+    let mut sink = Vec::new();
+    SecretBytes::new_with(3, |b| b.copy_from_slice(b"abc"))
+        .unwrap()
+        .write_to(&mut sink)
+        .unwrap();
+    SecretString::try_from("dé")
+        .unwrap()
+        .write_to(&mut sink)
+        .unwrap();
+    assert_eq!(sink, "abcdé".as_bytes());
+}
+
+#[test]
+fn sink_that_stops_accepting_is_an_error() {
+    let mut secret = Secret::<4>::random().unwrap();
+    let mut full = [0u8; 2];
+    let err = secret.write_to(&mut &mut full[..]).err().unwrap();
+    let MemoryError::Io(err) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+}
+
+/// A secret larger than the pipe buffer, written to a non-blocking pipe,
+/// must wait out `EAGAIN` and arrive intact.
+#[cfg(unix)]
+#[test]
+fn fd_writes_survive_a_full_non_blocking_pipe() {
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let [read_fd, write_fd] = fds;
+    unsafe { libc::fcntl(write_fd, libc::F_SETFL, libc::O_NONBLOCK) };
+    let mut reader = unsafe { std::fs::File::from_raw_fd(read_fd) };
+    let drain = std::thread::spawn(move || {
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        out
+    });
+
+    let mut secret = SecretBytes::new_with(256 * 1024, |b| {
+        for (i, byte) in b.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+    })
+    .unwrap();
+    secret.write_to_fd(write_fd).unwrap();
+    unsafe { libc::close(write_fd) };
+
+    let out = drain.join().unwrap();
+    assert_eq!(out.len(), 256 * 1024);
+    assert!(out.iter().enumerate().all(|(i, &b)| b == i as u8));
+}
+
+#[cfg(unix)]
+#[test]
+fn closed_fd_is_an_error() {
+    let mut secret = Secret::<4>::random().unwrap();
+    assert!(secret.write_to_fd(-1).is_err());
+}